@@ -0,0 +1,514 @@
+use ast::{Expr, Opcode};
+use solver::Solver;
+use builtins::check_arity;
+use polynome::Polynome;
+use simplify;
+
+// Largest constant term whose divisors are enumerated by the rational root search.
+const MAX_SEARCH: i64 = 1_000_000_000_000;
+
+#[derive(Copy, Clone, PartialEq)]
+pub struct Rational {
+    pub num: i64,
+    pub den: i64,
+}
+
+fn gcd(a: i64, b: i64) -> i64 {
+    if b == 0 { a.abs() } else { gcd(b, a % b) }
+}
+
+fn overflow() -> String {
+    format!("integer overflow in exact arithmetic")
+}
+
+// i64::MIN is rejected too, so that negations and absolute values stay in range.
+fn checked(num: Option<i64>, den: Option<i64>) -> Result<Rational, String> {
+    match (num, den) {
+        (Some(num), Some(den)) if num != i64::MIN && den != i64::MIN => Ok(Rational::new(num, den)),
+        _ => Err(overflow()),
+    }
+}
+
+impl Rational {
+    pub fn new(num: i64, den: i64) -> Rational {
+        let d = gcd(num, den).max(1) * den.signum();
+
+        Rational { num: num / d, den: den / d }
+    }
+
+    pub fn from_int(n: i64) -> Rational {
+        Rational { num: n, den: 1 }
+    }
+
+    // x as printed, exact when it has at most 7 significant digits like the decimals typed in.
+    fn from_decimal(x: f32) -> Option<Rational> {
+        let text = format!("{}", x.abs());
+        let (int, frac) = match text.find('.') {
+            Some(i) => (&text[..i], &text[i + 1..]),
+            None => (&text[..], ""),
+        };
+        let digits = format!("{}{}", int, frac);
+
+        if digits.trim_start_matches('0').len() > 7 || frac.len() > 18 {
+            return None;
+        }
+        let num: i64 = digits.parse().ok()?;
+
+        Some(Rational::new(if x < 0.0 { -num } else { num }, 10i64.pow(frac.len() as u32)))
+    }
+
+    // A decimal, or else the first continued fraction convergent rounding to x itself, as 1 / 3 does
+    // for 0.33333334. Other values, such as 3.14159265, are errors rather than approximations.
+    pub fn from_f32(x: f32) -> Result<Rational, String> {
+        if let Some(rational) = Rational::from_decimal(x) {
+            return Ok(rational);
+        }
+        let (mut h0, mut h1, mut k0, mut k1) = (0i64, 1i64, 1i64, 0i64);
+        let mut rest = x as f64;
+
+        for _ in 0..20 {
+            let a = rest.floor();
+
+            if a.abs() > 1e12 {
+                break;
+            }
+            let (h2, k2) = (a as i64 * h1 + h0, a as i64 * k1 + k0);
+
+            h0 = h1; h1 = h2;
+            k0 = k1; k1 = k2;
+            if k1 > 10000 || (h1 as f64 / k1 as f64) as f32 == x || rest == a {
+                break;
+            }
+            rest = 1.0 / (rest - a);
+        }
+        if k1 == 0 || k1 > 10000 || (h1 as f64 / k1 as f64) as f32 != x {
+            Err(format!("{} has no exact rational form", x))
+        } else {
+            Ok(Rational::new(h1, k1))
+        }
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.num == 0
+    }
+
+    pub fn add(self, other: Rational) -> Result<Rational, String> {
+        let g = gcd(self.den, other.den);
+        let num = self.num.checked_mul(other.den / g)
+            .and_then(|a| other.num.checked_mul(self.den / g).and_then(|b| a.checked_add(b)));
+
+        checked(num, (self.den / g).checked_mul(other.den))
+    }
+
+    pub fn sub(self, other: Rational) -> Result<Rational, String> {
+        self.add(Rational { num: -other.num, den: other.den })
+    }
+
+    // Cross-cancels first so that only the reduced result has to fit.
+    pub fn mul(self, other: Rational) -> Result<Rational, String> {
+        let (g, h) = (gcd(self.num, other.den).max(1), gcd(other.num, self.den).max(1));
+
+        checked((self.num / g).checked_mul(other.num / h), (self.den / h).checked_mul(other.den / g))
+    }
+
+    pub fn div(self, other: Rational) -> Result<Rational, String> {
+        if other.is_zero() {
+            return Err(format!("division by zero"));
+        }
+        self.mul(Rational::new(other.den, other.num))
+    }
+
+    pub fn to_f32(self) -> f32 {
+        self.num as f32 / self.den as f32
+    }
+}
+
+// Dense univariate polynomial, lowest degree first.
+type Poly = Vec<Rational>;
+
+fn trim(mut a: Poly) -> Poly {
+    while a.len() > 1 && a[a.len() - 1].is_zero() {
+        a.pop();
+    }
+    a
+}
+
+fn degree(a: &Poly) -> usize {
+    a.len() - 1
+}
+
+fn is_one(a: &Poly) -> bool {
+    a.len() == 1 && a[0] == Rational::from_int(1)
+}
+
+fn lead(a: &Poly) -> Rational {
+    a[a.len() - 1]
+}
+
+fn poly_sub(a: &Poly, b: &Poly) -> Result<Poly, String> {
+    let mut res = vec![Rational::from_int(0); a.len().max(b.len())];
+
+    for (i, x) in a.iter().enumerate() {
+        res[i] = res[i].add(*x)?;
+    }
+    for (i, x) in b.iter().enumerate() {
+        res[i] = res[i].sub(*x)?;
+    }
+    Ok(trim(res))
+}
+
+fn monic(a: &Poly) -> Result<Poly, String> {
+    let l = lead(a);
+
+    a.iter().map(|x| x.div(l)).collect()
+}
+
+fn derivative(a: &Poly) -> Result<Poly, String> {
+    if a.len() == 1 {
+        return Ok(vec![Rational::from_int(0)]);
+    }
+    a.iter().enumerate().skip(1).map(|(i, x)| x.mul(Rational::from_int(i as i64))).collect()
+}
+
+fn divmod(a: &Poly, b: &Poly) -> Result<(Poly, Poly), String> {
+    let mut rest = a.clone();
+    let mut quotient = vec![Rational::from_int(0); (a.len() + 1).saturating_sub(b.len()).max(1)];
+
+    while rest.len() >= b.len() && !(rest.len() == 1 && rest[0].is_zero()) {
+        let shift = rest.len() - b.len();
+        let coef = lead(&rest).div(lead(b))?;
+
+        quotient[shift] = coef;
+        for (i, x) in b.iter().enumerate() {
+            rest[i + shift] = rest[i + shift].sub(x.mul(coef)?)?;
+        }
+        rest.pop();
+        if rest.is_empty() {
+            rest.push(Rational::from_int(0));
+        }
+        rest = trim(rest);
+    }
+    Ok((trim(quotient), rest))
+}
+
+fn poly_gcd(a: &Poly, b: &Poly) -> Result<Poly, String> {
+    let (mut a, mut b) = (a.clone(), b.clone());
+
+    while !(b.len() == 1 && b[0].is_zero()) {
+        let rest = divmod(&a, &b)?.1;
+
+        a = b;
+        b = rest;
+    }
+    monic(&a)
+}
+
+// Yun's algorithm: square-free factors of a monic polynomial with their multiplicity.
+fn square_free(a: &Poly) -> Result<Vec<(Poly, usize)>, String> {
+    let mut res = Vec::new();
+    let da = derivative(a)?;
+    let g = poly_gcd(a, &da)?;
+    let mut b = divmod(a, &g)?.0;
+    let mut d = poly_sub(&divmod(&da, &g)?.0, &derivative(&b)?)?;
+    let mut multiplicity = 1;
+
+    while degree(&b) > 0 {
+        let factor = poly_gcd(&b, &d)?;
+
+        b = divmod(&b, &factor)?.0;
+        d = poly_sub(&divmod(&d, &factor)?.0, &derivative(&b)?)?;
+        if !is_one(&factor) {
+            res.push((factor, multiplicity));
+        }
+        multiplicity += 1;
+    }
+    Ok(res)
+}
+
+// Scales a polynomial to integer coprime coefficients with a positive leading one.
+fn primitive(a: &Poly) -> Result<Vec<i64>, String> {
+    let lcm = a.iter().try_fold(1i64, |acc, x| (acc / gcd(acc, x.den)).checked_mul(x.den)).ok_or_else(overflow)?;
+    let ints = a.iter().map(|x| x.num.checked_mul(lcm / x.den).ok_or_else(overflow)).collect::<Result<Vec<i64>, String>>()?;
+    let content = ints.iter().fold(0, |acc, x| gcd(acc, *x)).max(1) * ints[ints.len() - 1].signum();
+
+    Ok(ints.iter().map(|x| x / content).collect())
+}
+
+// Trial division up to the square root, paired with the cofactors.
+fn divisors(n: i64) -> Result<Vec<i64>, String> {
+    let n = n.abs();
+
+    if n > MAX_SEARCH {
+        return Err(format!("{} is too large to search for rational roots", n));
+    }
+    let (mut small, mut large) = (Vec::new(), Vec::new());
+    let mut d = 1;
+
+    while d * d <= n {
+        if n % d == 0 {
+            small.push(d);
+            if d != n / d {
+                large.push(n / d);
+            }
+        }
+        d += 1;
+    }
+    small.extend(large.into_iter().rev());
+    Ok(small)
+}
+
+fn eval(a: &[i64], x: i64) -> Result<i64, String> {
+    a.iter().rev().try_fold(0i64, |acc, c| acc.checked_mul(x).and_then(|v| v.checked_add(*c))).ok_or_else(overflow)
+}
+
+fn to_poly(a: &[i64]) -> Poly {
+    a.iter().map(|x| Rational::from_int(*x)).collect()
+}
+
+fn divides(a: &Poly, b: &Poly) -> Result<Option<Poly>, String> {
+    let (quotient, rest) = divmod(a, b)?;
+
+    Ok(if rest.len() == 1 && rest[0].is_zero() { Some(quotient) } else { None })
+}
+
+fn is_root(a: &[i64], p: i64, q: i64) -> Result<bool, String> {
+    let n = a.len() as u32 - 1;
+    let sum = a.iter().enumerate().try_fold(0i128, |acc, (i, c)| {
+        (p as i128).checked_pow(i as u32)
+            .and_then(|x| (q as i128).checked_pow(n - i as u32).and_then(|y| x.checked_mul(y)))
+            .and_then(|x| x.checked_mul(*c as i128))
+            .and_then(|x| acc.checked_add(x))
+    });
+
+    sum.map(|sum| sum == 0).ok_or_else(overflow)
+}
+
+// Candidates p / q in lowest terms, p dividing the constant term and q the leading coefficient.
+fn find_root(ints: &[i64]) -> Result<Option<(i64, i64)>, String> {
+    let leading = divisors(ints[ints.len() - 1])?;
+
+    for p in divisors(ints[0])? {
+        for &q in leading.iter().filter(|&&q| gcd(p, q) == 1) {
+            for &p in &[p, -p] {
+                if is_root(ints, p, q)? {
+                    return Ok(Some((p, q)));
+                }
+            }
+        }
+    }
+    Ok(None)
+}
+
+// Rational root theorem on the integer form of a square-free factor.
+fn rational_roots(a: &Poly) -> Result<(Vec<Poly>, Poly), String> {
+    let mut rest = a.clone();
+    let mut factors = Vec::new();
+
+    while degree(&rest) > 0 {
+        let ints = primitive(&rest)?;
+        let found = if ints[0] == 0 {
+            Some(vec![Rational::from_int(0), Rational::from_int(1)])
+        } else {
+            find_root(&ints)?.map(|(p, q)| vec![Rational::from_int(-p), Rational::from_int(q)])
+        };
+        match found {
+            Some(factor) => {
+                rest = divmod(&rest, &factor)?.0;
+                factors.push(factor);
+            },
+            None => break,
+        };
+    }
+    Ok((factors, rest))
+}
+
+// Kronecker's method restricted to quadratic factors, enough to split degree 4 and 5.
+fn quadratic_factor(a: &Poly) -> Result<Option<Poly>, String> {
+    let ints = primitive(a)?;
+    let values = [eval(&ints, 0)?, eval(&ints, 1)?, eval(&ints, -1)?];
+
+    if values.iter().any(|v| *v == 0 || v.abs() > 10000) {
+        return Ok(None);
+    }
+    let signed = |n: i64| divisors(n).map(|d| d.into_iter().flat_map(|d| vec![d, -d]).collect::<Vec<i64>>());
+
+    for c in signed(values[0])? {
+        for u in signed(values[1])? {
+            for v in signed(values[2])? {
+                if (u + v - 2 * c) % 2 != 0 || (u - v) % 2 != 0 || u + v - 2 * c <= 0 {
+                    continue;
+                }
+                let q = to_poly(&[c, (u - v) / 2, (u + v - 2 * c) / 2]);
+
+                if divides(&to_poly(&ints), &q)?.is_some() {
+                    return Ok(Some(q));
+                }
+            }
+        }
+    }
+    Ok(None)
+}
+
+fn irreducible_factors(a: &Poly) -> Result<Vec<Poly>, String> {
+    let (mut factors, mut rest) = rational_roots(a)?;
+
+    while degree(&rest) >= 4 {
+        match quadratic_factor(&rest)? {
+            Some(q) => {
+                rest = divmod(&rest, &q)?.0;
+                factors.push(q);
+            },
+            None => break,
+        };
+    }
+    if degree(&rest) > 0 {
+        factors.push(rest);
+    }
+    Ok(factors)
+}
+
+fn poly_to_expr(a: &[i64], var: &str) -> Expr {
+    let mut polynome = Polynome::constant(0.0);
+
+    for (i, c) in a.iter().enumerate() {
+        polynome = polynome.add(&Polynome::variable(var).pow(i as u32).scale(*c as f32));
+    }
+    polynome.to_expr()
+}
+
+fn factor_polynome(polynome: &Polynome) -> Result<Expr, String> {
+    let vars = polynome.variables();
+
+    if vars.len() > 1 {
+        return Err(format!("expected a univariate polynomial, found {} unknowns", vars.len()));
+    }
+    if polynome.degree() == 0 {
+        return Ok(Expr::Number(polynome.as_constant().unwrap_or(0.0)));
+    }
+    let var = vars[0].clone();
+    let mut coefs = vec![Rational::from_int(0); polynome.degree() as usize + 1];
+
+    for (monome, coef) in polynome.terms.iter() {
+        let n = monome.iter().map(|&(_, n)| n).sum::<u32>() as usize;
+
+        coefs[n] = Rational::from_f32(*coef)?;
+    }
+    let mut constant = lead(&coefs);
+    let mut factors: Vec<(Vec<i64>, usize)> = Vec::new();
+
+    for (part, multiplicity) in square_free(&monic(&coefs)?)? {
+        for factor in irreducible_factors(&part)? {
+            let ints = primitive(&factor)?;
+
+            for _ in 0..multiplicity {
+                constant = constant.div(Rational::from_int(ints[ints.len() - 1]))?;
+            }
+            factors.push((ints, multiplicity));
+        }
+    }
+    factors.sort_by(|a, b| a.0.len().cmp(&b.0.len()).then(a.0.cmp(&b.0)));
+    let res = factors.into_iter().fold(None, |acc, (ints, multiplicity)| {
+        let factor = match multiplicity {
+            1 => poly_to_expr(&ints, &var),
+            n => Expr::Op(Box::new(poly_to_expr(&ints, &var)), Opcode::Pow, Box::new(Expr::Number(n as f32))),
+        };
+
+        match acc {
+            Some(acc) => Some(Expr::Op(Box::new(acc), Opcode::Mul, Box::new(factor))),
+            None => Some(factor),
+        }
+    }).unwrap();
+
+    if constant == Rational::from_int(1) {
+        Ok(res)
+    } else {
+        Ok(Expr::Op(Box::new(Expr::Number(constant.to_f32())), Opcode::Mul, Box::new(res)))
+    }
+}
+
+pub fn expand(solver: &Solver, args: Vec<Expr>) -> Result<Expr, String> {
+    check_arity("expand", &args, 1, 1)?;
+    let expr = simplify::expand(solver.eval(args[0].clone())?)?;
+
+    match Polynome::from_expr(&expr) {
+        Some(polynome) => Ok(polynome.to_expr()),
+        None => Ok(expr),
+    }
+}
+
+pub fn factor(solver: &Solver, args: Vec<Expr>) -> Result<Expr, String> {
+    check_arity("factor", &args, 1, 1)?;
+    let expr = simplify::expand(solver.eval(args[0].clone())?)?;
+
+    match Polynome::from_expr(&expr) {
+        Some(polynome) => factor_polynome(&polynome).map_err(|e| format!("factor: {}", e)),
+        None => Err(format!("factor: '{}' is not a polynomial", expr)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solver::tests::run;
+
+    fn factor(input: &str) -> Result<String, String> {
+        run(&format!("factor({})", input))
+    }
+
+    #[test]
+    fn rational_arithmetic() {
+        let (a, b) = (Rational::new(1, 6), Rational::new(-3, 4));
+
+        assert!(a.add(b).unwrap() == Rational::new(-7, 12));
+        assert!(a.sub(b).unwrap() == Rational::new(11, 12));
+        assert!(a.mul(b).unwrap() == Rational::new(-1, 8));
+        assert!(a.div(b).unwrap() == Rational::new(-2, 9));
+        assert!(a.div(Rational::from_int(0)).is_err());
+    }
+
+    #[test]
+    fn rational_overflow() {
+        let big = Rational::from_int(i64::max_value() / 2);
+
+        assert!(big.mul(Rational::from_int(4)).is_err());
+        assert!(big.add(big).unwrap().add(big).is_err());
+        assert!(Rational::new(1, i64::max_value()).add(Rational::new(1, i64::max_value() - 1)).is_err());
+        // Cross-cancelling keeps products of large reciprocals in range.
+        assert!(big.mul(Rational::new(1, i64::max_value() / 2)).unwrap() == Rational::from_int(1));
+    }
+
+    #[test]
+    fn rational_conversion() {
+        assert!(Rational::from_f32(0.125).unwrap() == Rational::new(1, 8));
+        assert!(Rational::from_f32(-0.0000001).unwrap() == Rational::new(-1, 10000000));
+        assert!(Rational::from_f32(1.0 / 3.0).unwrap() == Rational::new(1, 3));
+        assert!(Rational::from_f32(1.2345678).is_err());
+    }
+
+    #[test]
+    fn expansions() {
+        assert_eq!(run("expand((x+1)^3)").unwrap(), "x ^ 3 + 3 * x ^ 2 + 3 * x + 1");
+        assert_eq!(run("expand((x+y)*(x-y))").unwrap(), "x ^ 2 - y ^ 2");
+        assert_eq!(run("expand(sin(x)*(x+1))").unwrap(), "sin(x) * x + sin(x)");
+        assert_eq!(run("expand(x^(-2) * (x + 1))").unwrap(), "1 / x + 1 / x ^ 2");
+    }
+
+    #[test]
+    fn factors() {
+        assert_eq!(factor("x^2 - 1").unwrap(), "(x - 1) * (x + 1)");
+        assert_eq!(factor("2*x^3 - 3*x^2 - 11*x + 6").unwrap(), "(x - 3) * (2 * x - 1) * (x + 2)");
+        assert_eq!(factor("x^4 + 4").unwrap(), "(x ^ 2 - 2 * x + 2) * (x ^ 2 + 2 * x + 2)");
+        assert_eq!(factor("x^3 - 2*x^2 + x").unwrap(), "(x - 1) ^ 2 * x");
+        assert_eq!(factor("x^2/3 - 1/3").unwrap(), "0.33333334 * (x - 1) * (x + 1)");
+        assert_eq!(factor("x^2 + 0.123456*x").unwrap(), "0.000064 * x * (15625 * x + 1929)");
+        assert_eq!(factor("x^2 + 3.14159265*x").unwrap_err(), "factor: 3.1415927 has no exact rational form");
+    }
+
+    #[test]
+    fn large_coefficients_are_errors() {
+        assert!(factor("x^2 - 999999995904").is_ok());
+        assert!(factor("0.5*x^2 + 999999995904").unwrap_err().contains("too large"));
+        assert!(factor("x^5 + 999999995904").unwrap_err().contains("overflow"));
+        assert!(factor("x*y + 1").is_err());
+    }
+}
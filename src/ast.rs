@@ -1,4 +1,6 @@
 use std::f32;
+use sparse::Sparse;
+use tensor::Tensor;
 
 pub trait Pow<RHS=Self> {
     type Output;
@@ -33,23 +35,14 @@ pub enum Opcode {
 #[derive(Debug)]
 pub enum Input {
     Assignation(Box<Expr>, Box<Expr>),
+    Equation(Box<Expr>, Box<Expr>),
     Eval(Box<Expr>)
 }
 
-pub enum AbstractType {
-    Computable(Expr),
-    Litteral(Expr),
-    Matrix(Expr),
-}
-
-impl AbstractType {
-    pub fn get_expr(self) -> Expr {
-        match self {
-            AbstractType::Computable(e) |
-            AbstractType::Litteral(e) |
-            AbstractType::Matrix(e) => e
-        }
-    }
+#[derive(Clone)]
+pub enum Slice {
+    Single(Box<Expr>),
+    Range(Option<Box<Expr>>, Option<Box<Expr>>),
 }
 
 #[derive(Clone)]
@@ -57,9 +50,14 @@ pub enum Expr {
     Number(f32),
     Imaginary,
     Complex(f32, f32),
-    Matrix(Vec<Vec<Box<Expr>>>),
+    Vector(Vec<Expr>),
+    Matrix(Tensor),
+    Sparse(Sparse),
     Variable(String),
     Function(String, Box<Expr>),
+    List(Vec<Box<Expr>>),
+    Text(String),
+    Index(Box<Expr>, Vec<Slice>),
     Op(Box<Expr>, Opcode, Box<Expr>)
 }
 
@@ -70,32 +68,39 @@ impl Expr {
                 Expr::Number(_) => format!("number ({})", left),
                 Expr::Imaginary => format!("complex ({})", left),
                 Expr::Complex(_, _) => format!("complex ({})", left),
-                Expr::Matrix(_) => format!("matrice ({:?})", left),
+                Expr::Vector(_) => format!("vector ({:?})", left),
+                Expr::Matrix(ref t) if t.shape.len() == 2 => format!("matrice ({:?})", left),
+                Expr::Matrix(_) => format!("tensor ({:?})", left),
+                Expr::Sparse(_) => format!("sparse matrice ({:?})", left),
                 Expr::Variable(_) => format!("variable ({})", left),
                 Expr::Function(_, _) => format!("function ({})", left),
+                Expr::List(_) => format!("list ({})", left),
+                Expr::Text(_) => format!("text ({})", left),
+                Expr::Index(_, _) => format!("index ({})", left),
                 Expr::Op(_, _, _) => format!("expression ({})", left),
             },
             match right {
                 Expr::Number(_) => format!("number ({})", right),
                 Expr::Imaginary => format!("complex ({})", right),
                 Expr::Complex(_, _) => format!("complex ({})", right),
-                Expr::Matrix(_) => format!("matrice ({:?})", right),
+                Expr::Vector(_) => format!("vector ({:?})", right),
+                Expr::Matrix(ref t) if t.shape.len() == 2 => format!("matrice ({:?})", right),
+                Expr::Matrix(_) => format!("tensor ({:?})", right),
+                Expr::Sparse(_) => format!("sparse matrice ({:?})", right),
                 Expr::Variable(_) => format!("variable ({})", right),
                 Expr::Function(_, _) => format!("function ({})", right),
+                Expr::List(_) => format!("list ({})", right),
+                Expr::Text(_) => format!("text ({})", right),
+                Expr::Index(_, _) => format!("index ({})", right),
                 Expr::Op(_, _, _) => format!("expression ({})", right),
             }
         )
     }
 
-    pub fn get_abstract_type(self) -> AbstractType {
-        match self {
-            Expr::Number(a) => AbstractType::Computable(Expr::Number(a)),
-            Expr::Complex(a, b) => AbstractType::Computable(Expr::Complex(a, b)),
-            Expr::Imaginary => AbstractType::Computable(Expr::Imaginary),
-            Expr::Variable(s) => AbstractType::Litteral(Expr::Variable(s)),
-            Expr::Function(s, a) => AbstractType::Litteral(Expr::Function(s, a)),
-            Expr::Matrix(v) => AbstractType::Matrix(Expr::Matrix(v)),
-            a => AbstractType::Litteral(a)
-        }
+    // Rows of the same length, as built by the matrice operations.
+    pub fn matrix(rows: Vec<Vec<Box<Expr>>>) -> Expr {
+        let shape = vec![rows.len(), rows[0].len()];
+
+        Expr::Matrix(Tensor { shape, data: rows.into_iter().flat_map(|line| line.into_iter().map(|e| *e)).collect() })
     }
 }
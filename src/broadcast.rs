@@ -0,0 +1,127 @@
+use ast::Expr;
+use linalg::Complex;
+use fast_path::Buffer;
+
+type Matrice = Vec<Vec<Box<Expr>>>;
+
+// Applies op on every cell, collecting the errors of each cell.
+fn element_wise<G, F>(size: (usize, usize), get: G, op: F) -> Result<Expr, String>
+    where G: Fn(usize, usize) -> (Expr, Expr), F: Fn(Expr, Expr) -> Result<Expr, String> {
+    let mut res = Vec::<Vec<Box<Expr>>>::new();
+    let mut errors = String::new();
+
+    for y in 0..size.0 {
+        let mut res_line = Vec::<Box<Expr>>::new();
+
+        for x in 0..size.1 {
+            let (left, right) = get(y, x);
+
+            res_line.push(match op(left, right) {
+                Ok(a) => Box::new(a),
+                Err(s) => {
+                    errors = if errors.is_empty() {
+                        format!("{} at [{}, {}]", s, x, y)
+                    } else {
+                        format!("{}\n{} at [{}, {}]", errors, s, x, y)
+                    };
+                    Box::new(Expr::Number(0.0))
+                },
+            });
+        }
+        res.push(res_line);
+    }
+    if errors.is_empty() {
+        Ok(Expr::matrix(res))
+    } else {
+        Err(errors)
+    }
+}
+
+pub fn is_scalar(expr: &Expr) -> bool {
+    match *expr {
+        Expr::Number(_) | Expr::Complex(_, _) | Expr::Imaginary => true,
+        _ => false,
+    }
+}
+
+// matrix op scalar, cell by cell.
+pub fn matrix_any<F>(a: Matrice, b: Expr, op: F) -> Result<Expr, String> where F: Fn(Expr, Expr) -> Result<Expr, String> {
+    element_wise((a.len(), a[0].len()), |y, x| (*a[y][x].clone(), b.clone()), op)
+}
+
+// scalar op matrix, cell by cell.
+pub fn any_matrix<F>(a: Expr, b: Matrice, op: F) -> Result<Expr, String> where F: Fn(Expr, Expr) -> Result<Expr, String> {
+    element_wise((b.len(), b[0].len()), |y, x| (a.clone(), *b[y][x].clone()), op)
+}
+
+// Shapes must match, except that a dimension of size 1 is stretched like a row or column vector.
+fn check_shapes(a: &Matrice, b: &Matrice) -> Result<(usize, usize), String> {
+    let stretch = |a: usize, b: usize| match (a, b) {
+        (a, b) if a == b => Some(a),
+        (1, b) => Some(b),
+        (a, 1) => Some(a),
+        _ => None,
+    };
+
+    match (stretch(a.len(), b.len()), stretch(a[0].len(), b[0].len())) {
+        (Some(y), Some(x)) => Ok((y, x)),
+        _ => Err(format!("invalid dimensions\n\texpected: [{}, {}]\n\tfound: [{}, {}]",
+                         a[0].len(), a.len(), b[0].len(), b.len())),
+    }
+}
+
+pub fn matrix_matrix<F>(a: Matrice, b: Matrice, op: F) -> Result<Expr, String> where F: Fn(Expr, Expr) -> Result<Expr, String> {
+    let size = check_shapes(&a, &b)?;
+    let cell = |m: &Matrice, y: usize, x: usize| *m[y % m.len()][x % m[0].len()].clone();
+
+    element_wise(size, |y, x| (cell(&a, y, x), cell(&b, y, x)), op)
+}
+
+// Same as matrix_matrix, on plain floats when both matrices are numeric,
+// op taking over whenever numeric gives up on an entry.
+pub fn numeric_matrix_matrix<F, G>(a: Matrice, b: Matrice, numeric: G, op: F) -> Result<Expr, String>
+    where F: Fn(Expr, Expr) -> Result<Expr, String>, G: Fn(Complex, Complex) -> Option<Complex> {
+    let size = check_shapes(&a, &b)?;
+
+    match (Buffer::from_matrice(&a), Buffer::from_matrice(&b)) {
+        (Some(x), Some(y)) => match x.zip(&y, size, numeric) {
+            Some(res) => Ok(res.into_expr()),
+            None => matrix_matrix(a, b, op),
+        },
+        _ => matrix_matrix(a, b, op),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use solver::tests::run;
+
+    #[test]
+    fn scalars() {
+        assert_eq!(run("[[1,2];[3,4]] + 1").unwrap(), "[ [2, 3] ; [4, 5] ]");
+        assert_eq!(run("1 - [[1,2];[3,4]]").unwrap(), "[ [0, -1] ; [-2, -3] ]");
+        assert_eq!(run("[[1,2];[3,4]] * 2").unwrap(), "[ [2, 4] ; [6, 8] ]");
+        assert_eq!(run("[[5,6];[7,8]] % 3").unwrap(), "[ [2, 0] ; [1, 2] ]");
+        assert_eq!(run("10 % [[3,4]]").unwrap(), "[ [1, 2] ]");
+        assert_eq!(run("[[x,1]] + 2").unwrap(), "[ [x + 2, 3] ]");
+    }
+
+    #[test]
+    fn row_and_column_vectors() {
+        assert_eq!(run("[[1,2];[3,4]] + [[10,20]]").unwrap(), "[ [11, 22] ; [13, 24] ]");
+        assert_eq!(run("[[1,2];[3,4]] - [[1];[2]]").unwrap(), "[ [0, 1] ; [1, 2] ]");
+        assert_eq!(run("[[1,2];[3,4]] * [[10,20]]").unwrap(), "[ [10, 40] ; [30, 80] ]");
+        assert_eq!(run("[[5,6];[7,8]] % [[2];[3]]").unwrap(), "[ [1, 0] ; [1, 2] ]");
+        assert_eq!(run("[[1];[2]] + [[10,20]]").unwrap(), "[ [11, 21] ; [12, 22] ]");
+        assert_eq!(run("[[x,1]] + [[1];[2]]").unwrap(), "[ [x + 1, 2] ; [x + 2, 3] ]");
+    }
+
+    #[test]
+    fn mismatched_shapes() {
+        assert_eq!(run("[[1,2];[3,4]] + [[1,2,3]]").unwrap_err(), "invalid dimensions\n\texpected: [2, 2]\n\tfound: [3, 1]");
+        assert_eq!(run("[[1,2];[3,4]] * [[1];[2];[3]]").unwrap_err(), "invalid dimensions\n\texpected: [2, 2]\n\tfound: [1, 3]");
+        assert_eq!(run("[[1,2,3]] % [[1,2]]").unwrap_err(), "invalid dimensions\n\texpected: [3, 1]\n\tfound: [2, 1]");
+        assert_eq!(run("[[1,2];[3,4]] - [[1,2,3];[4,5,6];[7,8,9]]").unwrap_err(),
+                   "invalid dimensions\n\texpected: [2, 2]\n\tfound: [3, 3]");
+    }
+}
@@ -0,0 +1,123 @@
+use ast::Expr;
+use solver::Solver;
+use root_finding::{root, solve};
+use algebra::{expand, factor};
+use derivative::diff;
+use integral::integrate;
+use quadrature::nintegrate;
+use linalg::{det, inv, linsolve, rref, rank, kernel, image, transpose, ctranspose, trace};
+use decomposition::{lu, qr, chol, svd};
+use eigen::eig;
+use exponential::expm;
+use construct::{eye, zeros, ones, diag, range, rand};
+use reshape::{hstack, vstack, reshape, map};
+use reduction::{sum, prod, min, max};
+use sparse::{self, sparse, full, load};
+use tensor::{shape, contract, tensordot, permute};
+use vector::{to_column, dot, cross, norm, normalize, proj, gramschmidt};
+use elementary::{elementary, is_elementary};
+
+pub fn get_args(expr: Expr) -> Vec<Expr> {
+    match expr {
+        Expr::List(list) => list.into_iter().map(|e| *e).collect(),
+        e => vec![e],
+    }
+}
+
+pub fn check_arity(name: &str, args: &[Expr], min: usize, max: usize) -> Result<(), String> {
+    match args.len() {
+        n if n >= min && n <= max => Ok(()),
+        n if min == max => Err(format!("{} expects {} arguments, found {}", name, min, n)),
+        n => Err(format!("{} expects {} to {} arguments, found {}", name, min, max, n)),
+    }
+}
+
+pub fn get_number(solver: &Solver, expr: Expr) -> Result<f32, String> {
+    match solver.eval(expr)? {
+        Expr::Number(a) => Ok(a),
+        e => Err(format!("expected a number, found '{}'", e)),
+    }
+}
+
+pub fn get_matrix(solver: &Solver, expr: Expr) -> Result<Vec<Vec<Box<Expr>>>, String> {
+    match solver.eval(expr)? {
+        Expr::Matrix(t) => t.into_rows(),
+        Expr::Sparse(_) => Err(sparse::unsupported("this operation")),
+        Expr::Vector(vector) => Ok(to_column(vector)),
+        e => Err(format!("expected a matrice, found '{}'", e)),
+    }
+}
+
+pub fn get_name(expr: Expr) -> Result<String, String> {
+    match expr {
+        Expr::Variable(s) => Ok(s.to_lowercase()),
+        e => Err(format!("expected a name, found '{}'", e)),
+    }
+}
+
+// Builtins whose result is an expression of their arguments, computed once when defining a function.
+pub fn is_symbolic(name: &str) -> bool {
+    match name {
+        "diff" | "integrate" | "expand" | "factor" => true,
+        _ => false,
+    }
+}
+
+pub fn call_builtin(solver: &Solver, name: &str, arg: Expr) -> Result<Expr, String> {
+    let args = get_args(arg);
+
+    match name {
+        "root" => root(solver, args),
+        "solve" => solve(solver, args),
+        "expand" => expand(solver, args),
+        "factor" => factor(solver, args),
+        "diff" => diff(solver, args),
+        "integrate" => integrate(solver, args),
+        "nintegrate" => nintegrate(solver, args),
+        "det" => det(solver, args),
+        "inv" => inv(solver, args),
+        "linsolve" => linsolve(solver, args),
+        "rref" => rref(solver, args),
+        "rank" => rank(solver, args),
+        "kernel" => kernel(solver, args),
+        "image" => image(solver, args),
+        "transpose" => transpose(solver, args),
+        "ctranspose" => ctranspose(solver, args),
+        "trace" => trace(solver, args),
+        "lu" => lu(solver, args),
+        "qr" => qr(solver, args),
+        "chol" => chol(solver, args),
+        "svd" => svd(solver, args),
+        "eig" => eig(solver, args),
+        "expm" => expm(solver, args),
+        "eye" => eye(solver, args),
+        "zeros" => zeros(solver, args),
+        "ones" => ones(solver, args),
+        "diag" => diag(solver, args),
+        "range" => range(solver, args),
+        "rand" => rand(solver, args),
+        "hstack" => hstack(solver, args),
+        "vstack" => vstack(solver, args),
+        "reshape" => reshape(solver, args),
+        "map" => map(solver, args),
+        "sum" => sum(solver, args),
+        "prod" => prod(solver, args),
+        "min" => min(solver, args),
+        "max" => max(solver, args),
+        "sparse" => sparse(solver, args),
+        "full" => full(solver, args),
+        "load" => load(solver, args),
+        "shape" => shape(solver, args),
+        "contract" => contract(solver, args),
+        "tensordot" => tensordot(solver, args),
+        "permute" => permute(solver, args),
+        "dot" => dot(solver, args),
+        "cross" => cross(solver, args),
+        "norm" => norm(solver, args),
+        "normalize" => normalize(solver, args),
+        "proj" => proj(solver, args),
+        "gramschmidt" => gramschmidt(solver, args),
+        name if is_elementary(name) => elementary(solver, name, args),
+        _ => Err(format!("Error: Function '{}' is undefined.", name)),
+    }
+}
@@ -1,6 +1,7 @@
 use ast::Expr;
 use std::cmp::{Ordering, Eq};
 use std::f32;
+use sparse::Sparse;
 
 impl PartialOrd for Expr {
     fn partial_cmp(&self, other: &Expr) -> Option<Ordering> {
@@ -17,23 +18,6 @@ impl PartialOrd for Expr {
     }
 }
 
-fn cmp_matrix_matrix(a: Vec<Vec<Box<Expr>>>, b: Vec<Vec<Box<Expr>>>) -> bool {
-    if a.len() != b.len() || a[0].len() != b[0].len() {
-        false
-    } else {
-        let mut state = true;
-        for (line_a, line_b) in a.iter().zip(b.iter()) {
-            for (value_a, value_b) in line_a.iter().zip(line_b.iter()) {
-                if value_a != value_b {
-                    state = false;
-                    break;
-                }
-            }
-        }
-        state
-    }
-}
-
 impl PartialEq for Expr {
     fn eq(&self, other: &Expr) -> bool {
         match (self.clone(), other.clone()) {
@@ -47,7 +31,11 @@ impl PartialEq for Expr {
                 b >= y - f32::EPSILON && b <= y + f32::EPSILON,
             (Expr::Complex(_, b), Expr::Imaginary) | (Expr::Imaginary, Expr::Complex(_, b)) =>
                 b >= 1.0 - f32::EPSILON && b <= 1.0 + f32::EPSILON,
-            (Expr::Matrix(a), Expr::Matrix(b)) => cmp_matrix_matrix(a, b),
+            (Expr::Matrix(a), Expr::Matrix(b)) => a.shape == b.shape && a.data == b.data,
+            (Expr::Vector(a), Expr::Vector(b)) => a == b,
+            (Expr::Sparse(a), Expr::Sparse(b)) => a.approx_eq(&b),
+            (Expr::Sparse(a), Expr::Matrix(b)) | (Expr::Matrix(b), Expr::Sparse(a)) =>
+                b.into_rows().ok().and_then(|b| Sparse::from_matrice(&b)).is_some_and(|b| a.approx_eq(&b)),
             _ => false,
         }
     }
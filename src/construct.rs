@@ -0,0 +1,158 @@
+use ast::Expr;
+use solver::Solver;
+use builtins::{check_arity, get_number};
+use linalg::{from_rows, identity_matrice, to_rows};
+use vector::get_vector;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+fn get_size(solver: &Solver, name: &str, expr: Expr) -> Result<usize, String> {
+    match get_number(solver, expr)? {
+        n if n >= 1.0 && n == n.round() => Ok(n as usize),
+        n => Err(format!("{}: expected a positive integer size, found {}", name, n)),
+    }
+}
+
+// (rows, columns) from one or two arguments, a single size making a square matrice.
+fn get_shape(solver: &Solver, name: &str, args: Vec<Expr>) -> Result<(usize, usize), String> {
+    let n = get_size(solver, name, args[0].clone())?;
+
+    match args.len() {
+        1 => Ok((n, n)),
+        _ => Ok((n, get_size(solver, name, args[1].clone())?)),
+    }
+}
+
+fn filled(shape: (usize, usize), value: f32) -> Expr {
+    from_rows((0..shape.0).map(|_| (0..shape.1).map(|_| Expr::Number(value)).collect()).collect())
+}
+
+pub fn eye(solver: &Solver, args: Vec<Expr>) -> Result<Expr, String> {
+    check_arity("eye", &args, 1, 1)?;
+    Ok(identity_matrice(get_size(solver, "eye", args[0].clone())?))
+}
+
+pub fn zeros(solver: &Solver, args: Vec<Expr>) -> Result<Expr, String> {
+    check_arity("zeros", &args, 1, 2)?;
+    Ok(filled(get_shape(solver, "zeros", args)?, 0.0))
+}
+
+pub fn ones(solver: &Solver, args: Vec<Expr>) -> Result<Expr, String> {
+    check_arity("ones", &args, 1, 2)?;
+    Ok(filled(get_shape(solver, "ones", args)?, 1.0))
+}
+
+// Diagonal matrice from a vector, or the diagonal of a matrice as a row vector.
+pub fn diag(solver: &Solver, args: Vec<Expr>) -> Result<Expr, String> {
+    check_arity("diag", &args, 1, 1)?;
+    match solver.eval(args[0].clone())? {
+        Expr::Matrix(t) if t.shape.len() == 2 && t.shape[0] > 1 && t.shape[1] > 1 => {
+            let m = t.shape[1];
+            let rows = to_rows(t.into_rows()?);
+
+            Ok(from_rows(vec![rows.into_iter().enumerate().take(m).map(|(i, mut line)| line.swap_remove(i)).collect()]))
+        },
+        e => {
+            let values = get_vector(solver, "diag", e)?;
+            let size = values.len();
+
+            Ok(from_rows((0..size).map(|i| (0..size).map(|j| {
+                if i == j { values[i].clone() } else { Expr::Number(0.0) }
+            }).collect()).collect()))
+        },
+    }
+}
+
+// Row vector from start up to, excluding, end.
+pub fn range(solver: &Solver, args: Vec<Expr>) -> Result<Expr, String> {
+    check_arity("range", &args, 1, 3)?;
+    let numbers: Result<Vec<f32>, String> = args.into_iter().map(|e| get_number(solver, e)).collect();
+    let (start, end, step) = match *numbers?.as_slice() {
+        [end] => (0.0, end, 1.0),
+        [start, end] => (start, end, 1.0),
+        [start, end, step] => (start, end, step),
+        _ => unreachable!(),
+    };
+
+    if step == 0.0 {
+        return Err(format!("range: step can't be 0"));
+    }
+    let count = ((end - start) / step - 1e-5).ceil();
+
+    if count < 1.0 {
+        return Err(format!("range: empty range from {} to {} by {}", start, end, step));
+    }
+    Ok(from_rows(vec![(0..count as usize).map(|i| Expr::Number(start + i as f32 * step)).collect()]))
+}
+
+// SplitMix64, uniform in [0, 1).
+fn next_random(state: &mut u64) -> f32 {
+    *state = state.wrapping_add(0x9E3779B97F4A7C15);
+    let mut z = *state;
+
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+    z ^= z >> 31;
+    (z >> 40) as f32 / (1u64 << 24) as f32
+}
+
+pub fn rand(solver: &Solver, args: Vec<Expr>) -> Result<Expr, String> {
+    check_arity("rand", &args, 2, 3)?;
+    let shape = get_shape(solver, "rand", args[..2].to_vec())?;
+    let mut state = match args.get(2) {
+        Some(seed) => get_number(solver, seed.clone())? as u64,
+        None => SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs() ^ d.subsec_nanos() as u64).unwrap_or(0),
+    };
+
+    Ok(from_rows((0..shape.0).map(|_| (0..shape.1).map(|_| Expr::Number(next_random(&mut state))).collect()).collect()))
+}
+
+#[cfg(test)]
+mod tests {
+    use ast::Expr;
+    use solver::Solver;
+    use solver::tests::run;
+
+    #[test]
+    fn filled_matrices() {
+        assert_eq!(run("eye(2)").unwrap(), "[ [1, 0] ; [0, 1] ]");
+        assert_eq!(run("zeros(2, 3)").unwrap(), "[ [0, 0, 0] ; [0, 0, 0] ]");
+        assert_eq!(run("ones(2)").unwrap(), "[ [1, 1] ; [1, 1] ]");
+    }
+
+    #[test]
+    fn invalid_sizes() {
+        for line in &["eye(0)", "eye((-1))", "eye(1.5)", "zeros(2, 0)", "ones(1.5, 2)", "rand(0, 2, 1)"] {
+            assert!(run(line).unwrap_err().contains("expected a positive integer size"), "{}", line);
+        }
+    }
+
+    #[test]
+    fn ranges() {
+        assert_eq!(run("range(4)").unwrap(), "[ [0, 1, 2, 3] ]");
+        assert_eq!(run("range(1, 2, 0.25)").unwrap(), "[ [1, 1.25, 1.5, 1.75] ]");
+        assert_eq!(run("range(3, 0, (-1))").unwrap(), "[ [3, 2, 1] ]");
+        assert!(run("range(0, 3, (-1))").unwrap_err().contains("empty range"));
+        assert!(run("range(1, 2, 0)").unwrap_err().contains("step can't be 0"));
+    }
+
+    #[test]
+    fn seeded_random_matrices() {
+        assert_eq!(run("rand(2, 3, 42)").unwrap(), run("rand(2, 3, 42)").unwrap());
+        assert!(run("rand(2, 3, 42)").unwrap() != run("rand(2, 3, 7)").unwrap());
+        match Solver::new().run("rand(4, 4) = ?").unwrap() {
+            Expr::Matrix(t) => {
+                assert_eq!(t.shape, vec![4, 4]);
+                assert!(t.data.iter().all(|e| match *e { Expr::Number(a) => a >= 0.0 && a < 1.0, _ => false }));
+            },
+            e => panic!("unexpected {}", e),
+        }
+    }
+
+    #[test]
+    fn diagonals() {
+        assert_eq!(run("diag([1,2,3])").unwrap(), "[ [1, 0, 0] ; [0, 2, 0] ; [0, 0, 3] ]");
+        assert_eq!(run("diag([[1,2]])").unwrap(), "[ [1, 0] ; [0, 2] ]");
+        assert_eq!(run("diag([[1,2];[3,4];[5,6]])").unwrap(), "[ [1, 4] ]");
+        assert!(run("diag(3)").unwrap_err().contains("expected a vector"));
+    }
+}
@@ -0,0 +1,307 @@
+use ast::Expr;
+use solver::Solver;
+use builtins::{check_arity, get_matrix};
+use linalg::{Complex, Numeric, c_abs, c_add, c_conj, c_div, c_mul, c_sub, check_square, from_numeric, from_rows,
+             identity, partial_pivot, to_numeric, to_rows, tolerance};
+use std::f64;
+
+const MAX_SWEEPS: usize = 60;
+
+pub fn get_numeric(solver: &Solver, name: &str, expr: Expr, square: bool) -> Result<Numeric, String> {
+    let rows = to_rows(get_matrix(solver, expr)?);
+
+    if square {
+        check_square(&rows).map_err(|s| format!("{}: {}", name, s))?;
+    }
+    match to_numeric(&rows) {
+        Some(numeric) => Ok(numeric),
+        None => Err(format!("{}: expected numeric entries", name)),
+    }
+}
+
+pub fn to_matrix(rows: Numeric) -> Expr {
+    from_rows(from_numeric(rows))
+}
+
+pub fn to_list(matrices: Vec<Numeric>) -> Expr {
+    Expr::List(matrices.into_iter().map(|m| Box::new(to_matrix(m))).collect())
+}
+
+fn zeros(n: usize, m: usize) -> Numeric {
+    vec![vec![(0.0, 0.0); m]; n]
+}
+
+// P * M = L * U with partial pivoting.
+pub fn lu(solver: &Solver, args: Vec<Expr>) -> Result<Expr, String> {
+    check_arity("lu", &args, 1, 1)?;
+    let mut u = get_numeric(solver, "lu", args[0].clone(), true)?;
+    let n = u.len();
+    let epsilon = tolerance(&u);
+    let mut l = identity(n);
+    let mut p = identity(n);
+
+    for k in 0..n {
+        let pivot = partial_pivot(&u, k, k);
+
+        u.swap(pivot, k);
+        p.swap(pivot, k);
+        if pivot != k {
+            let (top, bottom) = l.split_at_mut(pivot);
+
+            top[k][..k].swap_with_slice(&mut bottom[0][..k]);
+        }
+        if c_abs(u[k][k]) <= epsilon {
+            continue;
+        }
+        let row = u[k].clone();
+
+        for i in (k + 1)..n {
+            let factor = c_div(u[i][k], row[k]);
+
+            l[i][k] = factor;
+            for (x, &y) in u[i].iter_mut().zip(row.iter()).skip(k) {
+                *x = c_sub(*x, c_mul(factor, y));
+            }
+            u[i][k] = (0.0, 0.0);
+        }
+    }
+    Ok(to_list(vec![p, l, u]))
+}
+
+// M = Q * R with Householder reflections, Q unitary.
+pub fn householder(mut r: Numeric) -> (Numeric, Numeric) {
+    let (n, m) = (r.len(), r[0].len());
+    let epsilon = tolerance(&r);
+    let mut q = identity(n);
+
+    for k in 0..m.min(n - 1) {
+        let norm = (k..n).map(|i| c_abs(r[i][k]).powi(2)).sum::<f64>().sqrt();
+
+        if norm <= epsilon {
+            continue;
+        }
+        let phase = if c_abs(r[k][k]) > 0.0 { (r[k][k].0 / c_abs(r[k][k]), r[k][k].1 / c_abs(r[k][k])) } else { (1.0, 0.0) };
+        let mut v: Vec<Complex> = (0..n).map(|i| if i < k { (0.0, 0.0) } else { r[i][k] }).collect();
+
+        v[k] = c_add(v[k], (phase.0 * norm, phase.1 * norm));
+        let squared: f64 = v.iter().map(|&a| c_abs(a).powi(2)).sum();
+
+        let s: Vec<Complex> = (0..m).map(|j| {
+            let s = (k..n).fold((0.0, 0.0), |acc, i| c_add(acc, c_mul(c_conj(v[i]), r[i][j])));
+
+            (2.0 * s.0 / squared, 2.0 * s.1 / squared)
+        }).collect();
+
+        for (line, &vi) in r.iter_mut().zip(v.iter()).skip(k) {
+            for (x, &sj) in line.iter_mut().zip(s.iter()) {
+                *x = c_sub(*x, c_mul(vi, sj));
+            }
+        }
+        for line in q.iter_mut() {
+            let s = (k..n).fold((0.0, 0.0), |acc, l| c_add(acc, c_mul(line[l], v[l])));
+            let s = (2.0 * s.0 / squared, 2.0 * s.1 / squared);
+
+            for l in k..n {
+                line[l] = c_sub(line[l], c_mul(s, c_conj(v[l])));
+            }
+        }
+        for line in r.iter_mut().skip(k + 1) {
+            line[k] = (0.0, 0.0);
+        }
+    }
+    (q, r)
+}
+
+pub fn qr(solver: &Solver, args: Vec<Expr>) -> Result<Expr, String> {
+    check_arity("qr", &args, 1, 1)?;
+    let (q, r) = householder(get_numeric(solver, "qr", args[0].clone(), false)?);
+
+    Ok(to_list(vec![q, r]))
+}
+
+// M = L * L* for hermitian positive definite matrices.
+pub fn chol(solver: &Solver, args: Vec<Expr>) -> Result<Expr, String> {
+    check_arity("chol", &args, 1, 1)?;
+    let a = get_numeric(solver, "chol", args[0].clone(), true)?;
+    let n = a.len();
+    let epsilon = tolerance(&a);
+    let mut l = zeros(n, n);
+
+    if (0..n).any(|i| (0..i).any(|j| c_abs(c_sub(a[i][j], c_conj(a[j][i]))) > epsilon)) {
+        return Err(format!("chol: matrice is not symmetric"));
+    }
+    for j in 0..n {
+        let diagonal = a[j][j].0 - (0..j).map(|k| c_abs(l[j][k]).powi(2)).sum::<f64>();
+
+        if diagonal <= epsilon {
+            return Err(format!("chol: matrice is not positive definite"));
+        }
+        l[j][j] = (diagonal.sqrt(), 0.0);
+        for i in (j + 1)..n {
+            let s = (0..j).fold(a[i][j], |acc, k| c_sub(acc, c_mul(l[i][k], c_conj(l[j][k]))));
+
+            l[i][j] = c_div(s, l[j][j]);
+        }
+    }
+    Ok(to_matrix(l))
+}
+
+fn transpose(a: &[Vec<f64>]) -> Vec<Vec<f64>> {
+    (0..a[0].len()).map(|j| a.iter().map(|line| line[j]).collect()).collect()
+}
+
+// One-sided Jacobi on the columns of a, with at least as many rows as columns: a = u * diag(s) * v^T.
+fn jacobi_svd(mut u: Vec<Vec<f64>>) -> (Vec<Vec<f64>>, Vec<f64>, Vec<Vec<f64>>) {
+    let (n, m) = (u.len(), u[0].len());
+    let mut v: Vec<Vec<f64>> = (0..m).map(|i| (0..m).map(|j| if i == j { 1.0 } else { 0.0 }).collect()).collect();
+
+    for _ in 0..MAX_SWEEPS {
+        let mut rotated = false;
+
+        for p in 0..m {
+            for q in (p + 1)..m {
+                let alpha: f64 = (0..n).map(|i| u[i][p] * u[i][p]).sum();
+                let beta: f64 = (0..n).map(|i| u[i][q] * u[i][q]).sum();
+                let gamma: f64 = (0..n).map(|i| u[i][p] * u[i][q]).sum();
+
+                if gamma.abs() <= 1e-15 * (alpha * beta).sqrt() || gamma == 0.0 {
+                    continue;
+                }
+                rotated = true;
+                let zeta = (beta - alpha) / (2.0 * gamma);
+                let t = zeta.signum() / (zeta.abs() + (1.0 + zeta * zeta).sqrt());
+                let c = 1.0 / (1.0 + t * t).sqrt();
+                let s = c * t;
+
+                for line in u.iter_mut().chain(v.iter_mut()) {
+                    let (x, y) = (line[p], line[q]);
+
+                    line[p] = c * x - s * y;
+                    line[q] = s * x + c * y;
+                }
+            }
+        }
+        if !rotated {
+            break;
+        }
+    }
+    let norms: Vec<f64> = (0..m).map(|j| (0..n).map(|i| u[i][j] * u[i][j]).sum::<f64>().sqrt()).collect();
+    let mut order: Vec<usize> = (0..m).collect();
+
+    order.sort_by(|&a, &b| norms[b].partial_cmp(&norms[a]).unwrap());
+    let u = (0..n).map(|i| order.iter().map(|&j| if norms[j] > 0.0 { u[i][j] / norms[j] } else { 0.0 }).collect()).collect();
+    let v = (0..m).map(|i| order.iter().map(|&j| v[i][j]).collect()).collect();
+
+    (u, order.iter().map(|&j| norms[j]).collect(), v)
+}
+
+// M = U * S * V^T, real matrices only.
+pub fn svd(solver: &Solver, args: Vec<Expr>) -> Result<Expr, String> {
+    check_arity("svd", &args, 1, 1)?;
+    let a = get_numeric(solver, "svd", args[0].clone(), false)?;
+
+    if a.iter().any(|line| line.iter().any(|&(_, b)| b != 0.0)) {
+        return Err(format!("svd: expected real entries"));
+    }
+    let a: Vec<Vec<f64>> = a.into_iter().map(|line| line.into_iter().map(|(x, _)| x).collect()).collect();
+    let (u, s, v) = if a.len() >= a[0].len() {
+        jacobi_svd(a)
+    } else {
+        let (u, s, v) = jacobi_svd(transpose(&a));
+
+        (v, s, u)
+    };
+    let epsilon = s.first().cloned().unwrap_or(0.0) * s.len() as f64 * f64::EPSILON;
+    let sigma = (0..s.len()).map(|i| (0..s.len()).map(|j| {
+        if i == j && s[i] > epsilon { (s[i], 0.0) } else { (0.0, 0.0) }
+    }).collect()).collect();
+    let complex = |a: Vec<Vec<f64>>| a.into_iter().map(|line| line.into_iter().map(|x| (x, 0.0)).collect()).collect();
+
+    Ok(to_list(vec![complex(u), sigma, complex(v)]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use linalg::c_product;
+
+    const M: &str = "[[2,1,1];[4,3,3];[8,7,9]]";
+
+    fn factors(line: &str) -> Result<Vec<Numeric>, String> {
+        let numeric = |e: &Expr| match *e {
+            Expr::Matrix(ref m) => to_numeric(&to_rows(m.clone().into_rows().unwrap())).unwrap(),
+            ref e => panic!("{}: not a matrix {}", line, e),
+        };
+
+        match Solver::new().run(&format!("{} = ?", line))? {
+            Expr::List(list) => Ok(list.iter().map(|e| numeric(e)).collect()),
+            e => Ok(vec![numeric(&e)]),
+        }
+    }
+
+    fn matrix(input: &str) -> Numeric {
+        factors(input).unwrap().remove(0)
+    }
+
+    fn transposed(a: &Numeric, conjugate: bool) -> Numeric {
+        (0..a[0].len()).map(|j| a.iter().map(|line| if conjugate { c_conj(line[j]) } else { line[j] }).collect()).collect()
+    }
+
+    fn assert_close(a: &Numeric, b: &Numeric) {
+        assert_eq!((a.len(), a[0].len()), (b.len(), b[0].len()));
+        for (x, y) in a.iter().flat_map(|line| line.iter()).zip(b.iter().flat_map(|line| line.iter())) {
+            assert!(c_abs(c_sub(*x, *y)) < 1e-5, "{:?} != {:?}", a, b);
+        }
+    }
+
+    fn is_upper(a: &Numeric) -> bool {
+        a.iter().enumerate().all(|(i, line)| line.iter().take(i.min(line.len())).all(|x| c_abs(*x) < 1e-6))
+    }
+
+    #[test]
+    fn lu_decomposition() {
+        let plu = factors(&format!("lu({})", M)).unwrap();
+
+        assert_close(&c_product(&plu[0], &matrix(M)), &c_product(&plu[1], &plu[2]));
+        assert!(is_upper(&plu[2]) && is_upper(&transposed(&plu[1], false)));
+        // Singular matrices keep a zero pivot instead of failing.
+        let plu = factors("lu([[1,2];[2,4]])").unwrap();
+
+        assert_close(&c_product(&plu[0], &matrix("[[1,2];[2,4]]")), &c_product(&plu[1], &plu[2]));
+        assert!(factors("lu([[1,2,3];[4,5,6]])").unwrap_err().contains("non-square"));
+    }
+
+    #[test]
+    fn qr_decomposition() {
+        for input in &[M, "[[1,2];[3,4];[5,6]]", "[[1,i];[0,2]]"] {
+            let qr = factors(&format!("qr({})", input)).unwrap();
+            let n = qr[0].len();
+
+            assert_close(&c_product(&qr[0], &qr[1]), &matrix(input));
+            assert_close(&c_product(&transposed(&qr[0], true), &qr[0]), &identity(n));
+            assert!(is_upper(&qr[1]));
+        }
+    }
+
+    #[test]
+    fn cholesky() {
+        let input = "[[4,2,0];[2,5,3];[0,3,10]]";
+        let l = matrix(&format!("chol({})", input));
+
+        assert_close(&c_product(&l, &transposed(&l, true)), &matrix(input));
+        assert!(is_upper(&transposed(&l, false)));
+        assert!(factors("chol([[1,2];[3,4]])").unwrap_err().contains("not symmetric"));
+        assert!(factors("chol([[1,2];[2,1]])").unwrap_err().contains("not positive definite"));
+    }
+
+    #[test]
+    fn singular_values() {
+        for input in &[M, "[[1,2];[3,4];[5,6]]", "[[1,2,3];[4,5,6]]", "[[1,2];[2,4]]"] {
+            let usv = factors(&format!("svd({})", input)).unwrap();
+
+            assert_close(&c_product(&c_product(&usv[0], &usv[1]), &transposed(&usv[2], false)), &matrix(input));
+            assert!(usv[1].windows(2).enumerate().all(|(i, w)| w[0][i].0 >= w[1][i + 1].0));
+        }
+        assert!(factors("svd([[1,i];[0,2]])").unwrap_err().contains("real entries"));
+    }
+}
@@ -0,0 +1,144 @@
+use ast::{Expr, Opcode, Pow, Slice};
+use solver::Solver;
+use builtins::{check_arity, get_name};
+use elementary::is_elementary;
+use tensor::Tensor;
+use vector::map_entries;
+
+fn function(name: &str, arg: Expr) -> Expr {
+    Expr::Function(name.to_string(), Box::new(arg))
+}
+
+pub fn depends(expr: &Expr, var: &str) -> bool {
+    match *expr {
+        Expr::Variable(ref s) => s.to_lowercase() == var,
+        Expr::Function(_, ref arg) => depends(arg, var),
+        Expr::Op(ref a, _, ref b) => depends(a, var) || depends(b, var),
+        Expr::Matrix(ref t) => t.data.iter().any(|e| depends(e, var)),
+        Expr::Vector(ref vector) => vector.iter().any(|e| depends(e, var)),
+        Expr::Index(ref e, ref slices) => depends(e, var) || slices.iter().any(|slice| match *slice {
+            Slice::Single(ref e) => depends(e, var),
+            Slice::Range(ref a, ref b) => a.iter().chain(b.iter()).any(|e| depends(e, var)),
+        }),
+        Expr::List(ref list) => list.iter().any(|e| depends(e, var)),
+        _ => false,
+    }
+}
+
+// Derivative of the builtin f at u, before the chain rule.
+fn derive_elementary(name: &str, u: Expr) -> Result<Expr, String> {
+    match name {
+        "sin" => Ok(function("cos", u)),
+        "cos" => Expr::Number(-1.0) * function("sin", u),
+        "tan" => Expr::Number(1.0) + function("tan", u).pow(Expr::Number(2.0))?,
+        "exp" => Ok(function("exp", u)),
+        "ln" => Expr::Number(1.0) / u,
+        "sqrt" => Expr::Number(0.5) / function("sqrt", u),
+        "abs" => u.clone() / function("abs", u),
+        _ => Err(format!("diff: unknown function '{}'", name)),
+    }
+}
+
+pub fn derive(solver: &Solver, expr: Expr, var: &str) -> Result<Expr, String> {
+    match expr {
+        Expr::Variable(ref s) if s.to_lowercase() == var => Ok(Expr::Number(1.0)),
+        Expr::Matrix(t) => {
+            let data = t.data.into_iter().map(|e| derive(solver, e, var)).collect::<Result<_, _>>()?;
+
+            Ok(Expr::Matrix(Tensor { shape: t.shape, data }))
+        },
+        Expr::Vector(vector) => map_entries(vector, |e| derive(solver, e, var)),
+        Expr::Function(s, arg) => match solver.get_function(&s) {
+            Some((arg_name, body)) => derive(solver, solver.substitute(body, &arg_name, &arg), var),
+            None if is_elementary(&s.to_lowercase()) =>
+                derive_elementary(&s.to_lowercase(), *arg.clone())? * derive(solver, *arg, var)?,
+            None => Err(format!("diff: can't derive '{}'", Expr::Function(s, arg))),
+        },
+        Expr::Op(a, op, b) => match op {
+            Opcode::Add => derive(solver, *a, var)? + derive(solver, *b, var)?,
+            Opcode::Sub => derive(solver, *a, var)? - derive(solver, *b, var)?,
+            Opcode::Mul =>
+                (derive(solver, *a.clone(), var)? * *b.clone())? + (*a * derive(solver, *b, var)?)?,
+            Opcode::Div => {
+                let numerator = ((derive(solver, *a.clone(), var)? * *b.clone())?
+                                 - (*a * derive(solver, *b.clone(), var)?)?)?;
+
+                numerator / (*b).pow(Expr::Number(2.0))?
+            },
+            Opcode::Pow if !depends(&b, var) => {
+                let exponent = (*b.clone() - Expr::Number(1.0))?;
+
+                (*b * (*a.clone()).pow(exponent)?)? * derive(solver, *a, var)?
+            },
+            Opcode::Pow => {
+                let inner = ((derive(solver, *b.clone(), var)? * function("ln", *a.clone()))?
+                             + ((*b.clone() * derive(solver, *a.clone(), var)?)? / *a.clone())?)?;
+
+                (*a).pow(*b)? * inner
+            },
+            op => Err(format!("diff: can't derive the {:?} operator", op)),
+        },
+        Expr::Variable(ref s) if solver.get_function(s).is_some() =>
+            Err(format!("diff: expected an expression, found function '{}'", s)),
+        Expr::Number(_) | Expr::Imaginary | Expr::Complex(_, _) | Expr::Variable(_) => Ok(Expr::Number(0.0)),
+        ref e @ Expr::Index(_, _) if !depends(e, var) => Ok(Expr::Number(0.0)),
+        e => Err(format!("diff: can't derive '{}'", e)),
+    }
+}
+
+pub fn diff(solver: &Solver, args: Vec<Expr>) -> Result<Expr, String> {
+    check_arity("diff", &args, 2, 2)?;
+    let var = get_name(args[1].clone())?;
+    let res = derive(solver, args[0].clone(), &var)?;
+
+    solver.solve_function(res, var.clone(), Expr::Variable(var))
+}
+
+#[cfg(test)]
+mod tests {
+    use solver::Solver;
+    use solver::tests::{run, run_in};
+
+    #[test]
+    fn sums_products_and_quotients() {
+        assert_eq!(run("diff(x^2 + 3 * x, x)").unwrap(), "2 * x + 3");
+        assert_eq!(run("diff(x * sin(x), x)").unwrap(), "cos(x) * x + sin(x)");
+        assert_eq!(run("diff(sin(x) / x, x)").unwrap(), "cos(x) / x - sin(x) / x ^ 2");
+    }
+
+    #[test]
+    fn chain_and_power_rules() {
+        assert_eq!(run("diff(sin(x^2), x)").unwrap(), "2 * cos(x ^ 2) * x");
+        assert_eq!(run("diff(exp(2 * x), x)").unwrap(), "2 * exp(2 * x)");
+        assert_eq!(run("diff(x^3, x)").unwrap(), "3 * x ^ 2");
+        assert_eq!(run("diff(x^(-1), x)").unwrap(), "-1 / x ^ 2");
+        assert_eq!(run("diff(2^x, x)").unwrap(), "0.6931472 * 2 ^ x");
+    }
+
+    #[test]
+    fn user_functions() {
+        let mut solver = Solver::new();
+
+        run_in(&mut solver, "f(x) = x^3 + x").unwrap();
+        assert_eq!(run_in(&mut solver, "diff(f(x), x) = ?").unwrap(), "3 * x ^ 2 + 1");
+        run_in(&mut solver, "g(x) = diff(f(x), x)").unwrap();
+        assert_eq!(run_in(&mut solver, "g(2) = ?").unwrap(), "13");
+        assert_eq!(run_in(&mut solver, "diff(f, x) = ?").unwrap_err(), "diff: expected an expression, found function 'f'");
+    }
+
+    #[test]
+    fn other_variables_are_constants() {
+        assert_eq!(run("diff(x^2, y)").unwrap(), "0");
+        assert_eq!(run("diff(y * x, y)").unwrap(), "x");
+    }
+
+    #[test]
+    fn vectors_and_indexes() {
+        let mut solver = Solver::new();
+
+        assert_eq!(run("diff([x, x^2, 3], x)").unwrap(), "[1, 2 * x, 0]");
+        run_in(&mut solver, "A = [[1,2];[3,4]]").unwrap();
+        assert_eq!(run_in(&mut solver, "diff(A[0, 1] * x, x) = ?").unwrap(), "2");
+        assert!(run("diff(\"a\", x)").unwrap_err().contains("can't derive"));
+    }
+}
@@ -1,6 +1,17 @@
 use ast::{Expr, Opcode};
 use std::ops::Div;
 use std::f32;
+use simplify::simplify;
+use broadcast::{is_scalar, numeric_matrix_matrix};
+use linalg::{Complex, c_div};
+use tensor::{element_wise, is_tensor};
+use vector;
+use sparse;
+
+// Divisors close to zero are left to the scalar arms, which report the error.
+fn div_numeric(a: Complex, b: Complex) -> Option<Complex> {
+    if b.0 * b.0 + b.1 * b.1 <= f32::EPSILON as f64 { None } else { Some(c_div(a, b)) }
+}
 
 fn div_number_number(a: f32, b: f32) -> Result<Expr, String> {
     if b >= 0.0 - f32::EPSILON && b <= 0.0 + f32::EPSILON {
@@ -11,7 +22,7 @@ fn div_number_number(a: f32, b: f32) -> Result<Expr, String> {
 }
 
 fn div_number_complex(a: f32, c: (f32, f32)) -> Result<Expr, String> {
-    let squared = c.0 * c.0 - c.1 * c.1;
+    let squared = c.0 * c.0 + c.1 * c.1;
 
     if squared >= 0.0 - f32::EPSILON && squared <= 0.0 + f32::EPSILON {
         Err(format!("Can't divide by 0"))
@@ -35,7 +46,7 @@ fn div_complex_complex(c0: (f32, f32), c1: (f32, f32)) -> Result<Expr, String> {
         Err(format!("Can't divide by 0"))
     } else {
         Ok(Expr::Complex(
-            (c0.0 * c1.0 + c0.1 + c1.1) / squared,
+            (c0.0 * c1.0 + c0.1 * c1.1) / squared,
             (c0.1 * c1.0 - c0.0 * c1.1) / squared
         ))
     }
@@ -59,7 +70,7 @@ fn div_complex_imaginary(c: (f32, f32)) -> Result<Expr, String> {
 }
 
 fn div_imaginary_complex(c: (f32, f32)) -> Result<Expr, String> {
-    let squared = c.0 * c.0 - c.1 * c.1;
+    let squared = c.0 * c.0 + c.1 * c.1;
     
     if squared >= 0.0 - f32::EPSILON && squared <= 0.0 + f32::EPSILON {
         Err(format!("Can't divide by 0"))
@@ -93,37 +104,7 @@ fn div_matrix_any(a: Vec<Vec<Box<Expr>>>, b: Expr) -> Result<Expr, String> {
         res.push(res_line);
     }
     if errors.is_empty() {
-        Ok(Expr::Matrix(res))
-    } else {
-        Err(errors)
-    }
-}
-
-fn div_matrix_matrix(a: Vec<Vec<Box<Expr>>>, b: Vec<Vec<Box<Expr>>>) -> Result<Expr, String> {
-    let mut res = Vec::<Vec<Box<Expr>>>::new();
-    let mut errors = String::new();
-
-    for (y, line) in a.iter().zip(b.iter()).enumerate() {
-        let mut res_line = Vec::<Box<Expr>>::new();
-
-        for (x, value) in line.0.iter().zip(line.1.iter()).enumerate() {
-            let (left, right) = (*value.0.clone(), *value.1.clone());
-            res_line.push(match left / right {
-                Ok(a) => Box::new(a),
-                Err(s) => {
-                    errors = if errors.is_empty() {
-                        format!("{} at [{}, {}]", s, x, y)
-                    } else {
-                        format!("{}\n{} at [{}, {}]", errors, s, x, y)
-                    };
-                    Box::new(Expr::Number(0.0))
-                },
-            });
-        }
-        res.push(res_line);
-    }
-    if errors.is_empty() {
-        Ok(Expr::Matrix(res))
+        Ok(Expr::matrix(res))
     } else {
         Err(errors)
     }
@@ -134,6 +115,8 @@ impl Div for Expr {
 
     fn div(self, other: Expr) -> Result<Expr, String> {
         match (self, other) {
+            (a, b) if is_tensor(&a) || is_tensor(&b) => element_wise(a, b, |x, y| x / y),
+            (a @ Expr::Vector(_), b) | (a, b @ Expr::Vector(_)) => vector::element_wise(a, b, |x, y| x / y),
             (Expr::Number(a), Expr::Number(b)) => div_number_number(a, b),
             (Expr::Number(a), Expr::Complex(ca, cb)) => div_number_complex(a, (ca, cb)),
             (Expr::Complex(ca, cb), Expr::Number(a)) => div_complex_number(a, (ca, cb)),
@@ -142,11 +125,16 @@ impl Div for Expr {
             (Expr::Imaginary, Expr::Number(a)) => div_imaginary_number(a),
             (Expr::Complex(ca, cb), Expr::Imaginary) => div_complex_imaginary((ca, cb)),
             (Expr::Imaginary, Expr::Complex(ca, cb)) => div_imaginary_complex((ca, cb)),
-            (Expr::Matrix(a), Expr::Number(b)) => div_matrix_any(a, Expr::Number(b)),
-            (Expr::Matrix(a), Expr::Complex(x, y)) => div_matrix_any(a, Expr::Complex(x, y)),
-            (Expr::Matrix(a), Expr::Imaginary) => div_matrix_any(a, Expr::Imaginary),
-            (Expr::Matrix(a), Expr::Matrix(b)) => div_matrix_matrix(a, b), //Kronecker div
-            (a, b) => Ok(Expr::Op(Box::new(a), Opcode::Div, Box::new(b))),
+            (Expr::Sparse(a), ref b) if is_scalar(b) => match a.scale(&(Expr::Number(1.0) / b.clone())?) {
+                Some(s) => Ok(Expr::Sparse(s)),
+                None => Err(sparse::unsupported("this division")),
+            },
+            (Expr::Sparse(_), _) | (_, Expr::Sparse(_)) => Err(sparse::unsupported("this division")),
+            (Expr::Matrix(a), Expr::Number(b)) => div_matrix_any(a.into_rows()?, Expr::Number(b)),
+            (Expr::Matrix(a), Expr::Complex(x, y)) => div_matrix_any(a.into_rows()?, Expr::Complex(x, y)),
+            (Expr::Matrix(a), Expr::Imaginary) => div_matrix_any(a.into_rows()?, Expr::Imaginary),
+            (Expr::Matrix(a), Expr::Matrix(b)) => numeric_matrix_matrix(a.into_rows()?, b.into_rows()?, div_numeric, |x, y| x / y), //Kronecker div
+            (a, b) => simplify(Expr::Op(Box::new(a), Opcode::Div, Box::new(b))),
         }
     }
 }
@@ -0,0 +1,283 @@
+use ast::Expr;
+use solver::Solver;
+use builtins::check_arity;
+use equation::polynomial_roots;
+use decomposition::{get_numeric, householder, to_matrix};
+use linalg::{Complex, Numeric, c_abs, c_add, c_div, c_mul, c_product, c_sqrt, c_sub, kernel_basis, reduce_rows};
+use std::f64;
+
+const MAX_ITERATIONS: usize = 1000;
+const MAX_SWEEPS: usize = 60;
+
+fn product(a: &[Vec<f64>], b: &[Vec<f64>]) -> Vec<Vec<f64>> {
+    (0..a.len()).map(|i| (0..b[0].len()).map(|j| (0..b.len()).map(|k| a[i][k] * b[k][j]).sum()).collect()).collect()
+}
+
+// Faddeev-LeVerrier, coefficients from the highest degree down.
+fn characteristic(a: &[Vec<f64>]) -> Vec<f64> {
+    let n = a.len();
+    let mut am = vec![vec![0.0; n]; n];
+    let mut coefs = vec![1.0];
+
+    for k in 1..(n + 1) {
+        let mut m = am;
+
+        for (i, line) in m.iter_mut().enumerate() {
+            line[i] += coefs[k - 1];
+        }
+        am = product(a, &m);
+        let trace: f64 = am.iter().enumerate().map(|(i, line)| line[i]).sum();
+
+        coefs.push(-trace / k as f64);
+    }
+    coefs
+}
+
+// Cyclic Jacobi rotations on a real symmetric matrix, eigenvectors as columns.
+fn jacobi_eigen(mut a: Vec<Vec<f64>>) -> (Vec<f64>, Vec<Vec<f64>>) {
+    let n = a.len();
+    let mut v: Vec<Vec<f64>> = (0..n).map(|i| (0..n).map(|j| if i == j { 1.0 } else { 0.0 }).collect()).collect();
+
+    for _ in 0..MAX_SWEEPS {
+        let off: f64 = (0..n).map(|i| (0..n).filter(|&j| j != i).map(|j| a[i][j] * a[i][j]).sum::<f64>()).sum();
+        let total: f64 = a.iter().map(|line| line.iter().map(|x| x * x).sum::<f64>()).sum();
+
+        if off <= f64::EPSILON * f64::EPSILON * total {
+            break;
+        }
+        for p in 0..n {
+            for q in (p + 1)..n {
+                if a[p][q] == 0.0 {
+                    continue;
+                }
+                let theta = (a[q][q] - a[p][p]) / (2.0 * a[p][q]);
+                let t = theta.signum() / (theta.abs() + (theta * theta + 1.0).sqrt());
+                let c = 1.0 / (t * t + 1.0).sqrt();
+                let s = t * c;
+
+                for line in a.iter_mut().chain(v.iter_mut()) {
+                    let (x, y) = (line[p], line[q]);
+
+                    line[p] = c * x - s * y;
+                    line[q] = s * x + c * y;
+                }
+                let (top, bottom) = a.split_at_mut(q);
+
+                for (x, y) in top[p].iter_mut().zip(bottom[0].iter_mut()) {
+                    let (u, w) = (*x, *y);
+
+                    *x = c * u - s * w;
+                    *y = s * u + c * w;
+                }
+            }
+        }
+    }
+    ((0..n).map(|i| a[i][i]).collect(), v)
+}
+
+// Shifted QR iteration, deflating one eigenvalue at a time from the bottom.
+fn qr_eigenvalues(mut a: Numeric) -> Result<Vec<Complex>, String> {
+    let norm = a.iter().flat_map(|line| line.iter().map(|&x| c_abs(x))).fold(0.0, f64::max);
+    let mut values = Vec::new();
+    let mut iterations = 0;
+
+    while !a.is_empty() {
+        let n = a.len();
+        let rest = (0..(n - 1)).map(|j| c_abs(a[n - 1][j])).fold(0.0, f64::max);
+
+        if n == 1 || rest <= f64::EPSILON * norm.max(f64::MIN_POSITIVE) {
+            values.push(a[n - 1][n - 1]);
+            a.pop();
+            for line in a.iter_mut() {
+                line.pop();
+            }
+            continue;
+        }
+        iterations += 1;
+        if iterations > MAX_ITERATIONS {
+            return Err(format!("eig: QR iteration did not converge"));
+        }
+        // Wilkinson shift, with an exceptional shift now and then to break cycles
+        let (x, y, z, w) = (a[n - 2][n - 2], a[n - 2][n - 1], a[n - 1][n - 2], a[n - 1][n - 1]);
+        let half = ((x.0 + w.0) / 2.0, (x.1 + w.1) / 2.0);
+        let root = c_sqrt(c_sub(c_mul(half, half), c_sub(c_mul(x, w), c_mul(y, z))));
+        let (l1, l2) = (c_add(half, root), c_sub(half, root));
+        let shift = match iterations % 11 {
+            0 => c_add(w, (rest, 0.0)),
+            _ if c_abs(c_sub(l1, w)) < c_abs(c_sub(l2, w)) => l1,
+            _ => l2,
+        };
+        for (i, line) in a.iter_mut().enumerate() {
+            line[i] = c_sub(line[i], shift);
+        }
+        let (q, r) = householder(a);
+
+        a = c_product(&r, &q);
+        for (i, line) in a.iter_mut().enumerate() {
+            line[i] = c_add(line[i], shift);
+        }
+    }
+    Ok(values)
+}
+
+fn snap(a: Complex, epsilon: f64) -> Complex {
+    (if a.0.abs() <= epsilon { 0.0 } else { a.0 }, if a.1.abs() <= epsilon { 0.0 } else { a.1 })
+}
+
+// Unit length, largest component real and positive.
+fn normalize(vector: Vec<Complex>) -> Vec<Complex> {
+    let length = vector.iter().map(|&a| c_abs(a).powi(2)).sum::<f64>().sqrt();
+    let largest = vector.iter().cloned().fold((0.0, 0.0), |best, a| if c_abs(a) > c_abs(best) { a } else { best });
+
+    if length == 0.0 {
+        return vector;
+    }
+    let phase = c_div((c_abs(largest) / length, 0.0), largest);
+
+    vector.into_iter().map(|a| snap(c_mul(a, phase), 1e-12)).collect()
+}
+
+// Eigenvectors from the kernel of M - l * I, the missing ones of a defective
+// matrice being left as zero columns.
+fn eigenvectors(a: &Numeric, values: &[Complex]) -> Vec<Vec<Complex>> {
+    let n = a.len();
+    let mut vectors = Vec::new();
+    let mut i = 0;
+
+    while i < values.len() {
+        let count = values[i..].iter().take_while(|&&l| c_abs(c_sub(l, values[i])) <= 1e-6 * (1.0 + c_abs(values[i]))).count();
+        let mut shifted = a.clone();
+
+        for (k, line) in shifted.iter_mut().enumerate() {
+            line[k] = c_sub(line[k], values[i]);
+        }
+        let pivots = reduce_rows(&mut shifted);
+        let mut kernel: Vec<Vec<Complex>> = kernel_basis(&shifted, &pivots, n).into_iter().take(count).map(normalize).collect();
+
+        while kernel.len() < count {
+            kernel.push(vec![(0.0, 0.0); n]);
+        }
+        vectors.extend(kernel);
+        i += count;
+    }
+    vectors
+}
+
+fn sort_values(values: &mut [Complex]) {
+    values.sort_by(|a, b| a.0.total_cmp(&b.0).then(a.1.total_cmp(&b.1)));
+}
+
+// Eigenvalues and eigenvectors as columns, zero columns standing for the
+// eigenvectors missing from defective matrices.
+pub fn eig(solver: &Solver, args: Vec<Expr>) -> Result<Expr, String> {
+    check_arity("eig", &args, 1, 1)?;
+    let a = get_numeric(solver, "eig", args[0].clone(), true)?;
+    let n = a.len();
+
+    if a.iter().any(|line| line.iter().any(|&(x, y)| !x.is_finite() || !y.is_finite())) {
+        return Err(format!("eig: expected finite entries"));
+    }
+    let norm = a.iter().flat_map(|line| line.iter().map(|&x| c_abs(x))).fold(0.0, f64::max);
+    let epsilon = 1e-12 * norm.max(1.0);
+    let real = a.iter().all(|line| line.iter().all(|&(_, b)| b == 0.0));
+    let symmetric = real && (0..n).all(|i| (0..i).all(|j| (a[i][j].0 - a[j][i].0).abs() <= epsilon));
+    let (values, vectors): (Vec<Complex>, Vec<Vec<Complex>>) = if symmetric {
+        let (values, v) = jacobi_eigen(a.iter().map(|line| line.iter().map(|&(x, _)| x).collect()).collect());
+        let mut order: Vec<usize> = (0..n).collect();
+
+        order.sort_by(|&i, &j| values[i].total_cmp(&values[j]));
+        (order.iter().map(|&i| (values[i], 0.0)).collect(),
+         order.iter().map(|&j| normalize((0..n).map(|i| (v[i][j], 0.0)).collect())).collect())
+    } else {
+        let mut values = if real && n <= 4 {
+            polynomial_roots(&characteristic(&a.iter().map(|line| line.iter().map(|&(x, _)| x).collect::<Vec<_>>()).collect::<Vec<_>>()))
+        } else {
+            qr_eigenvalues(a.clone())?
+        };
+        sort_values(&mut values);
+        let vectors = eigenvectors(&a, &values);
+
+        (values, vectors)
+    };
+    let values = values.into_iter().map(|l| vec![snap(l, epsilon)]).collect();
+    let vectors = (0..n).map(|i| vectors.iter().map(|v| v[i]).collect()).collect();
+
+    Ok(Expr::List(vec![Box::new(to_matrix(values)), Box::new(to_matrix(vectors))]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use linalg::{to_numeric, to_rows};
+
+    fn numeric(e: &Expr) -> Numeric {
+        match *e {
+            Expr::Matrix(ref m) => to_numeric(&to_rows(m.clone().into_rows().unwrap())).unwrap(),
+            ref e => panic!("not a matrix {}", e),
+        }
+    }
+
+    // Checks M * v = l * v for every pair and returns the eigenvalues and the count of zero columns.
+    fn eig(input: &str) -> Result<(Vec<Complex>, usize), String> {
+        let m = numeric(&Solver::new().run(&format!("{} = ?", input))?);
+        let list = match Solver::new().run(&format!("eig({}) = ?", input))? {
+            Expr::List(list) => list,
+            e => panic!("{}: unexpected {}", input, e),
+        };
+        let values: Vec<Complex> = numeric(&list[0]).into_iter().map(|line| line[0]).collect();
+        let vectors = numeric(&list[1]);
+        let missing = (0..values.len()).filter(|&j| vectors.iter().all(|line| line[j] == (0.0, 0.0))).count();
+
+        assert_eq!(list.len(), 2);
+        for (j, &l) in values.iter().enumerate() {
+            let v: Numeric = vectors.iter().map(|line| vec![line[j]]).collect();
+
+            for (x, y) in c_product(&m, &v).iter().zip(v.iter()) {
+                assert!(c_abs(c_sub(x[0], c_mul(l, y[0]))) < 1e-4, "{}: {:?} is not an eigenvector", input, v);
+            }
+        }
+        Ok((values, missing))
+    }
+
+    fn assert_values(found: &[Complex], expected: &[Complex]) {
+        assert_eq!(found.len(), expected.len());
+        for (a, b) in found.iter().zip(expected.iter()) {
+            assert!(c_abs(c_sub(*a, *b)) < 1e-4, "{:?} != {:?}", found, expected);
+        }
+    }
+
+    #[test]
+    fn characteristic_polynomial() {
+        assert_eq!(characteristic(&[vec![1.0, 2.0], vec![3.0, 4.0]]), vec![1.0, -5.0, -2.0]);
+        assert_eq!(characteristic(&[vec![2.0, 0.0, 0.0], vec![0.0, 3.0, 0.0], vec![0.0, 0.0, 4.0]]), vec![1.0, -9.0, 26.0, -24.0]);
+    }
+
+    #[test]
+    fn symmetric_matrices() {
+        assert_values(&eig("[[2,1];[1,2]]").unwrap().0, &[(1.0, 0.0), (3.0, 0.0)]);
+        assert_values(&eig("[[4,1,2];[1,3,0];[2,0,5]]").unwrap().0, &[(1.8549, 0.0), (3.4760, 0.0), (6.6691, 0.0)]);
+    }
+
+    #[test]
+    fn general_matrices() {
+        assert_values(&eig("[[1,2,3];[0,4,5];[0,0,6]]").unwrap().0, &[(1.0, 0.0), (4.0, 0.0), (6.0, 0.0)]);
+        assert_values(&eig("[[0,(-1)];[1,0]]").unwrap().0, &[(0.0, -1.0), (0.0, 1.0)]);
+        assert_values(&eig("[[1,i];[0,2]]").unwrap().0, &[(1.0, 0.0), (2.0, 0.0)]);
+        // Five rows go through the QR iteration.
+        assert_values(&eig("[[2,0,0,0,1];[0,3,0,0,0];[0,0,4,0,0];[0,0,0,5,0];[0,0,0,0,6]]").unwrap().0,
+                      &[(2.0, 0.0), (3.0, 0.0), (4.0, 0.0), (5.0, 0.0), (6.0, 0.0)]);
+    }
+
+    #[test]
+    fn defective_matrices() {
+        assert_eq!(eig("[[2,0];[0,3]]").unwrap().1, 0);
+        assert_eq!(eig("[[1,1];[0,1]]").unwrap().1, 1);
+        assert!(eig("[[1,2,3];[4,5,6]]").unwrap_err().contains("non-square"));
+    }
+
+    #[test]
+    fn non_finite_entries() {
+        assert!(Solver::new().run("eig([[exp(100),1];[1,2]]) = ?").unwrap_err().contains("finite"));
+        assert!(Solver::new().run("eig([[exp(100),1];[0,2]]) = ?").unwrap_err().contains("finite"));
+    }
+}
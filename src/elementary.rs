@@ -0,0 +1,36 @@
+use ast::Expr;
+use solver::Solver;
+use builtins::check_arity;
+
+pub fn is_elementary(name: &str) -> bool {
+    match name {
+        "sin" | "cos" | "tan" | "exp" | "ln" | "sqrt" | "abs" => true,
+        _ => false,
+    }
+}
+
+fn compute(name: &str, x: f32) -> Result<Expr, String> {
+    match name {
+        "sin" => Ok(Expr::Number(x.sin())),
+        "cos" => Ok(Expr::Number(x.cos())),
+        "tan" => Ok(Expr::Number(x.tan())),
+        "exp" => Ok(Expr::Number(x.exp())),
+        "ln" if x <= 0.0 => Err(format!("ln: undefined for {}", x)),
+        "ln" => Ok(Expr::Number(x.ln())),
+        "sqrt" if x < 0.0 => Ok(Expr::Complex(0.0, (-x).sqrt())),
+        "sqrt" => Ok(Expr::Number(x.sqrt())),
+        "abs" => Ok(Expr::Number(x.abs())),
+        _ => Err(format!("Error: Function '{}' is undefined.", name)),
+    }
+}
+
+pub fn elementary(solver: &Solver, name: &str, args: Vec<Expr>) -> Result<Expr, String> {
+    check_arity(name, &args, 1, 1)?;
+    match solver.eval(args[0].clone())? {
+        Expr::Number(x) => compute(name, x),
+        Expr::Complex(a, b) if name == "abs" => Ok(Expr::Number((a * a + b * b).sqrt())),
+        Expr::Complex(_, _) | Expr::Imaginary | Expr::Vector(_) | Expr::Matrix(_) | Expr::Sparse(_) | Expr::List(_) =>
+            Err(format!("{}: expected a real argument", name)),
+        arg => Ok(Expr::Function(name.to_string(), Box::new(arg))),
+    }
+}
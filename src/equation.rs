@@ -1,14 +1,483 @@
 use ast::*;
 use solver::Solver;
+use utils::{is_zero, cancel};
+use linalg::{Complex, c_add, c_div, c_mul, c_sqrt};
+use std::f32;
+use std::f64;
 
 pub enum Pattern {
-    Trinome(Box<Expr>, Box<Expr>, Box<Expr>),
-    Linear(Box<Expr>, Box<Expr>),
-    Polynome2id(Box<Expr>, Box<Expr>),
-    Polynome3id(Box<Expr>, Box<Expr>),
+    Constant(f32),
+    Linear(f32, f32),
+    Trinome(f32, f32, f32),
+    Polynome2id(f32, f32), // a * x^2 + b * x
+    Polynome3id(f32, f32), // a * x^2 + c
+    Cubic(f32, f32, f32, f32),
+    Quartic(f32, f32, f32, f32, f32),
+}
+
+pub enum Solutions {
+    AllReals,
+    NoSolution,
+    Roots(Vec<(Expr, usize)>),
+    Unsolvable,
+}
+
+pub struct Equation {
+    pub unknown: String,
+    pub reduced: Vec<f32>,
+    pub pattern: Option<Pattern>,
+    pub solutions: Solutions,
+}
+
+fn poly_trim(mut a: Vec<f32>) -> Vec<f32> {
+    while a.len() > 1 && a[a.len() - 1] == 0.0 {
+        a.pop();
+    }
+    a
+}
+
+fn poly_add(a: Vec<f32>, b: Vec<f32>) -> Vec<f32> {
+    let res = (0..a.len().max(b.len())).map(|i| {
+        let (x, y) = (a.get(i).cloned().unwrap_or(0.0), b.get(i).cloned().unwrap_or(0.0));
+
+        cancel(x + y, x.abs().max(y.abs()))
+    }).collect();
+
+    poly_trim(res)
+}
+
+fn poly_sub(a: Vec<f32>, b: Vec<f32>) -> Vec<f32> {
+    poly_add(a, b.iter().map(|x| -x).collect())
+}
+
+fn poly_mul(a: Vec<f32>, b: Vec<f32>) -> Vec<f32> {
+    let mut res = vec![0.0; a.len() + b.len() - 1];
+    let mut largest = vec![0.0f32; a.len() + b.len() - 1];
+
+    for (i, x) in a.iter().enumerate() {
+        for (j, y) in b.iter().enumerate() {
+            res[i + j] += x * y;
+            largest[i + j] = largest[i + j].max((x * y).abs());
+        }
+    }
+    poly_trim(res.into_iter().zip(largest).map(|(sum, largest)| cancel(sum, largest)).collect())
+}
+
+fn as_constant(a: &[f32]) -> Option<f32> {
+    if a.len() == 1 {
+        Some(a[0])
+    } else {
+        None
+    }
+}
+
+fn as_number(expr: Expr) -> Result<Vec<f32>, String> {
+    match expr {
+        Expr::Number(a) => Ok(vec![a]),
+        Expr::Complex(_, _) | Expr::Imaginary => Err(format!("complex coefficients are not supported")),
+        Expr::Matrix(_) => Err(format!("matrices are not supported in equations")),
+        e => Err(format!("can't reduce '{}' to a polynomial", e)),
+    }
+}
+
+struct Reducer<'a> {
+    solver: &'a Solver,
+    unknown: Option<String>,
+}
+
+impl<'a> Reducer<'a> {
+    fn handle_variable(&mut self, s: String, binding: &Option<(String, Vec<f32>)>) -> Result<Vec<f32>, String> {
+        let s = s.to_lowercase();
+
+        match *binding {
+            Some((ref name, ref value)) if *name == s => return Ok(value.clone()),
+            _ => (),
+        };
+        if let Some(value) = self.solver.get_variable(&s) {
+            return as_number(value);
+        }
+        match self.unknown.clone() {
+            Some(ref unknown) if *unknown != s =>
+                Err(format!("too many unknowns: '{}' and '{}'", unknown, s)),
+            _ => {
+                self.unknown = Some(s);
+                Ok(vec![0.0, 1.0])
+            }
+        }
+    }
+
+    fn handle_function(&mut self, s: String, arg: Expr, binding: &Option<(String, Vec<f32>)>) -> Result<Vec<f32>, String> {
+        let arg = self.reduce(arg, binding)?;
+
+        match as_constant(&arg) {
+            Some(a) => as_number(self.solver.eval(Expr::Function(s, Box::new(Expr::Number(a))))?),
+            None => match self.solver.get_function(&s) {
+                Some((arg_name, body)) => self.reduce(body, &Some((arg_name, arg))),
+                None => Err(format!("Error: Function '{}' is undefined.", s)),
+            },
+        }
+    }
+
+    fn handle_pow(&mut self, a: Vec<f32>, b: Vec<f32>) -> Result<Vec<f32>, String> {
+        match (as_constant(&a), as_constant(&b)) {
+            (Some(x), Some(n)) => Ok(vec![x.powf(n)]),
+            (_, Some(n)) if n >= 0.0 && is_zero(n - n.round()) => {
+                let mut res = vec![1.0];
+
+                for _ in 0..(n.round() as i32) {
+                    res = poly_mul(res, a.clone());
+                }
+                Ok(res)
+            },
+            (_, Some(n)) => Err(format!("invalid exponent for the unknown: {}", n)),
+            _ => Err(format!("the unknown can't be an exponent")),
+        }
+    }
+
+    fn reduce(&mut self, expr: Expr, binding: &Option<(String, Vec<f32>)>) -> Result<Vec<f32>, String> {
+        match expr {
+            Expr::Number(a) => Ok(vec![a]),
+            Expr::Variable(s) => self.handle_variable(s, binding),
+            Expr::Function(s, arg) => self.handle_function(s, *arg, binding),
+            Expr::Op(a, op, b) => {
+                let a = self.reduce(*a, binding)?;
+                let b = self.reduce(*b, binding)?;
+
+                match op {
+                    Opcode::Add => Ok(poly_add(a, b)),
+                    Opcode::Sub => Ok(poly_sub(a, b)),
+                    Opcode::Mul => Ok(poly_mul(a, b)),
+                    Opcode::Div => match as_constant(&b) {
+                        Some(n) if is_zero(n) => Err(format!("Can't divide by 0")),
+                        Some(n) => Ok(a.iter().map(|x| x / n).collect()),
+                        None => Err(format!("the unknown can't be a divisor")),
+                    },
+                    Opcode::Pow => self.handle_pow(a, b),
+                    op => Err(format!("Can't {:?} in an equation", op)),
+                }
+            },
+            e => as_number(e),
+        }
+    }
+}
+
+fn real_cbrt(a: f64) -> f64 {
+    if a < 0.0 { -(-a).cbrt() } else { a.cbrt() }
+}
+
+// Roots of z^2 + b * z + c with complex coefficients.
+fn solve_monic_quadratic(b: Complex, c: Complex) -> Vec<Complex> {
+    let delta = c_add(c_mul(b, b), (-4.0 * c.0, -4.0 * c.1));
+    let sqrt = c_sqrt(delta);
+
+    vec![
+        ((-b.0 - sqrt.0) / 2.0, (-b.1 - sqrt.1) / 2.0),
+        ((-b.0 + sqrt.0) / 2.0, (-b.1 + sqrt.1) / 2.0),
+    ]
+}
+
+// Cardano: roots of t^3 + p * t + q.
+fn solve_depressed_cubic(p: f64, q: f64) -> Vec<Complex> {
+    let epsilon = 1e-12 * (1.0 + p.abs() + q.abs());
+    let delta = -(4.0 * p * p * p + 27.0 * q * q);
+
+    if p.abs() < epsilon && q.abs() < epsilon {
+        vec![(0.0, 0.0); 3]
+    } else if delta.abs() < 1e-9 * (4.0 * (p * p * p).abs() + 27.0 * q * q) {
+        vec![(3.0 * q / p, 0.0), (-3.0 * q / (2.0 * p), 0.0), (-3.0 * q / (2.0 * p), 0.0)]
+    } else if delta > 0.0 {
+        let module = 2.0 * (-p / 3.0).sqrt();
+        let angle = ((3.0 * q / (2.0 * p)) * (-3.0 / p).sqrt()).max(-1.0).min(1.0).acos() / 3.0;
+
+        (0..3).map(|k| (module * (angle - 2.0 * f64::consts::PI * k as f64 / 3.0).cos(), 0.0)).collect()
+    } else {
+        let sqrt = (q * q / 4.0 + p * p * p / 27.0).sqrt();
+        let u = real_cbrt(-q / 2.0 + sqrt);
+        let v = real_cbrt(-q / 2.0 - sqrt);
+        let im = (u - v) * 3f64.sqrt() / 2.0;
+
+        vec![(u + v, 0.0), (-(u + v) / 2.0, -im), (-(u + v) / 2.0, im)]
+    }
+}
+
+// Ferrari: roots of y^4 + p * y^2 + q * y + r.
+fn solve_depressed_quartic(p: f64, q: f64, r: f64) -> Vec<Complex> {
+    if q.abs() < 1e-12 * (1.0 + p.abs() + r.abs()) {
+        return solve_monic_quadratic((p, 0.0), (r, 0.0)).into_iter()
+            .flat_map(|z| { let y = c_sqrt(z); vec![y, (-y.0, -y.1)] })
+            .collect();
+    }
+    // resolvent 8m^3 + 8pm^2 + (2p^2 - 8r)m - q^2, shifted to a depressed cubic by m = t - p / 3
+    let (b, c, d) = (p, (p * p - 4.0 * r) / 4.0, -q * q / 8.0);
+    let m = solve_depressed_cubic(c - b * b / 3.0, 2.0 * b * b * b / 27.0 - b * c / 3.0 + d).iter()
+        .filter(|t| t.1 == 0.0)
+        .map(|t| t.0 - b / 3.0)
+        .fold(f64::MIN, f64::max);
+    let s = (2.0 * m).sqrt();
+    let mut roots = solve_monic_quadratic((-s, 0.0), (p / 2.0 + m + q / (2.0 * s), 0.0));
+
+    roots.extend(solve_monic_quadratic((s, 0.0), (p / 2.0 + m - q / (2.0 * s), 0.0)));
+    roots
+}
+
+// Roots of a real polynomial of degree 1 to 4, coefficients from the highest degree down.
+pub fn polynomial_roots(coefs: &[f64]) -> Vec<Complex> {
+    match *coefs {
+        [a, b] => vec![(-b / a, 0.0)],
+        [a, b, c] => solve_monic_quadratic((b / a, 0.0), (c / a, 0.0)),
+        [a, b, c, d] => Pattern::solve_cubic(a, b, c, d),
+        [a, b, c, d, e] => Pattern::solve_quartic(a, b, c, d, e),
+        _ => vec![],
+    }
+}
+
+// Merges numerically equal roots into (root, multiplicity) pairs, real roots first.
+fn group_roots(roots: Vec<Complex>) -> Vec<(Expr, usize)> {
+    let mut groups: Vec<(Complex, usize)> = Vec::new();
+
+    for root in roots {
+        let tolerance = 1e-5 * (1.0 + (root.0 * root.0 + root.1 * root.1).sqrt());
+
+        match groups.iter().position(|&(r, _)| (r.0 - root.0).abs() < tolerance && (r.1 - root.1).abs() < tolerance) {
+            Some(index) => {
+                let (r, n) = groups[index];
+
+                groups[index] = (c_div(c_add(c_mul(r, (n as f64, 0.0)), root), ((n + 1) as f64, 0.0)), n + 1);
+            },
+            None => groups.push((root, 1)),
+        };
+    }
+    groups.sort_by(|&(a, _), &(b, _)| {
+        (a.1.abs() >= 1e-6).cmp(&(b.1.abs() >= 1e-6))
+            .then(a.0.partial_cmp(&b.0).unwrap())
+            .then(a.1.partial_cmp(&b.1).unwrap())
+    });
+    groups.into_iter().map(|(r, n)| {
+        let r = (r.0 + 0.0, r.1 + 0.0);
+
+        if r.1.abs() < 1e-6 * (1.0 + r.0.abs()) {
+            (Expr::Number(r.0 as f32), n)
+        } else {
+            (Expr::Complex(r.0 as f32, r.1 as f32), n)
+        }
+    }).collect()
 }
 
 impl Pattern {
-    pub new(expr: Expr) -> Option<Pattern> {
+    // The reduced form went through cancel, so negligible coefficients are exact zeros
+    // and small but meaningful ones keep their degree.
+    pub fn new(reduced: &[f32]) -> Option<Pattern> {
+        match *reduced {
+            [c] => Some(Pattern::Constant(c)),
+            [b, a] => Some(Pattern::Linear(a, b)),
+            [c, b, a] if c == 0.0 => Some(Pattern::Polynome2id(a, b)),
+            [c, b, a] if b == 0.0 => Some(Pattern::Polynome3id(a, c)),
+            [c, b, a] => Some(Pattern::Trinome(a, b, c)),
+            [d, c, b, a] => Some(Pattern::Cubic(a, b, c, d)),
+            [e, d, c, b, a] => Some(Pattern::Quartic(a, b, c, d, e)),
+            _ => None,
+        }
+    }
+
+    pub fn discriminant(&self) -> Option<f32> {
+        match *self {
+            Pattern::Trinome(a, b, c) => Some(b * b - 4.0 * a * c),
+            Pattern::Polynome2id(_, b) => Some(b * b),
+            Pattern::Polynome3id(a, c) => Some(-4.0 * a * c),
+            Pattern::Cubic(a, b, c, d) =>
+                Some(18.0 * a * b * c * d - 4.0 * b * b * b * d + b * b * c * c
+                     - 4.0 * a * c * c * c - 27.0 * a * a * d * d),
+            Pattern::Quartic(a, b, c, d, e) =>
+                Some(256.0 * a * a * a * e * e * e - 192.0 * a * a * b * d * e * e
+                     - 128.0 * a * a * c * c * e * e + 144.0 * a * a * c * d * d * e
+                     - 27.0 * a * a * d * d * d * d + 144.0 * a * b * b * c * e * e
+                     - 6.0 * a * b * b * d * d * e - 80.0 * a * b * c * c * d * e
+                     + 18.0 * a * b * c * d * d * d + 16.0 * a * c * c * c * c * e
+                     - 4.0 * a * c * c * c * d * d - 27.0 * b * b * b * b * e * e
+                     + 18.0 * b * b * b * c * d * e - 4.0 * b * b * b * d * d * d
+                     - 4.0 * b * b * c * c * c * e + b * b * c * c * d * d),
+            _ => None,
+        }
+    }
+
+    fn solve_square(a: f64, c: f64) -> Vec<Complex> {
+        let square = -c / a;
+
+        if square >= 0.0 {
+            vec![(-square.sqrt(), 0.0), (square.sqrt(), 0.0)]
+        } else {
+            vec![(0.0, -(-square).sqrt()), (0.0, (-square).sqrt())]
+        }
+    }
+
+    fn solve_cubic(a: f64, b: f64, c: f64, d: f64) -> Vec<Complex> {
+        let (b, c, d) = (b / a, c / a, d / a);
+        let p = c - b * b / 3.0;
+        let q = 2.0 * b * b * b / 27.0 - b * c / 3.0 + d;
+
+        solve_depressed_cubic(p, q).into_iter().map(|t| (t.0 - b / 3.0, t.1)).collect()
+    }
+
+    fn solve_quartic(a: f64, b: f64, c: f64, d: f64, e: f64) -> Vec<Complex> {
+        let (b, c, d, e) = (b / a, c / a, d / a, e / a);
+        let p = c - 3.0 * b * b / 8.0;
+        let q = b * b * b / 8.0 - b * c / 2.0 + d;
+        let r = -3.0 * b * b * b * b / 256.0 + b * b * c / 16.0 - b * d / 4.0 + e;
+
+        solve_depressed_quartic(p, q, r).into_iter().map(|y| (y.0 - b / 4.0, y.1)).collect()
+    }
+
+    pub fn solve(&self) -> Solutions {
+        let roots = match *self {
+            Pattern::Constant(c) if c == 0.0 => return Solutions::AllReals,
+            Pattern::Constant(_) => return Solutions::NoSolution,
+            Pattern::Linear(a, b) => vec![(-b as f64 / a as f64, 0.0)],
+            Pattern::Polynome2id(a, b) => vec![(0.0, 0.0), (-b as f64 / a as f64, 0.0)],
+            Pattern::Polynome3id(a, c) => Pattern::solve_square(a as f64, c as f64),
+            Pattern::Trinome(a, b, c) => solve_monic_quadratic((b as f64 / a as f64, 0.0), (c as f64 / a as f64, 0.0)),
+            Pattern::Cubic(a, b, c, d) => Pattern::solve_cubic(a as f64, b as f64, c as f64, d as f64),
+            Pattern::Quartic(a, b, c, d, e) =>
+                Pattern::solve_quartic(a as f64, b as f64, c as f64, d as f64, e as f64),
+        };
+
+        Solutions::Roots(group_roots(roots))
+    }
+}
+
+impl Equation {
+    pub fn new(solver: &Solver, left: Expr, right: Expr) -> Result<Equation, String> {
+        let mut reducer = Reducer { solver: solver, unknown: None };
+        let left = reducer.reduce(left, &None)?;
+        let right = reducer.reduce(right, &None)?;
+        let reduced = poly_sub(left, right);
+        let pattern = Pattern::new(&reduced);
+        let solutions = match pattern {
+            Some(ref p) => p.solve(),
+            None => Solutions::Unsolvable,
+        };
+
+        Ok(Equation {
+            unknown: reducer.unknown.unwrap_or(format!("x")),
+            reduced: reduced,
+            pattern: pattern,
+            solutions: solutions,
+        })
+    }
+
+    pub fn degree(&self) -> usize {
+        self.reduced.len() - 1
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use parser::EquExprParser;
+
+    fn equation(input: &str) -> Equation {
+        match EquExprParser::new().parse(input) {
+            Ok(Input::Equation(left, right)) => Equation::new(&Solver::new(), *left, *right).unwrap(),
+            _ => panic!("not an equation: {}", input),
+        }
+    }
+
+    fn roots(input: &str) -> Vec<(f32, f32, usize)> {
+        match equation(input).solutions {
+            Solutions::Roots(roots) => roots.into_iter().map(|(root, n)| match root {
+                Expr::Number(a) => (a, 0.0, n),
+                Expr::Complex(a, b) => (a, b, n),
+                e => panic!("unexpected root {}", e),
+            }).collect(),
+            _ => panic!("no roots for {}", input),
+        }
+    }
+
+    fn assert_roots(input: &str, expected: &[(f32, f32, usize)]) {
+        let found = roots(input);
+
+        assert_eq!(found.len(), expected.len(), "{}", input);
+        for (&(a, b, n), &(x, y, m)) in found.iter().zip(expected.iter()) {
+            assert!((a - x).abs() < 1e-4 && (b - y).abs() < 1e-4 && n == m, "{}: found {} {} {}", input, a, b, n);
+        }
+    }
+
+    #[test]
+    fn linear_and_constant() {
+        assert_roots("2 * x + 4 = 0 ?", &[(-2.0, 0.0, 1)]);
+        assert!(match equation("x = x ?").solutions { Solutions::AllReals => true, _ => false });
+        assert!(match equation("x = x + 1 ?").solutions { Solutions::NoSolution => true, _ => false });
+    }
+
+    #[test]
+    fn quadratic() {
+        assert_roots("x^2 - 3 * x + 2 = 0 ?", &[(1.0, 0.0, 1), (2.0, 0.0, 1)]);
+        assert_roots("x^2 - 2 * x + 1 = 0 ?", &[(1.0, 0.0, 2)]);
+        assert_roots("x^2 + 1 = 0 ?", &[(0.0, -1.0, 1), (0.0, 1.0, 1)]);
+        assert_roots("x^2 = 4 * x ?", &[(0.0, 0.0, 1), (4.0, 0.0, 1)]);
+    }
+
+    #[test]
+    fn cubic() {
+        assert_roots("x^3 - 6 * x^2 + 11 * x - 6 = 0 ?", &[(1.0, 0.0, 1), (2.0, 0.0, 1), (3.0, 0.0, 1)]);
+        assert_roots("x^3 - 3 * x + 2 = 0 ?", &[(-2.0, 0.0, 1), (1.0, 0.0, 2)]);
+        assert_roots("x^3 = 0 ?", &[(0.0, 0.0, 3)]);
+        assert_roots("x^3 - 1 = 0 ?", &[(1.0, 0.0, 1), (-0.5, -0.8660254, 1), (-0.5, 0.8660254, 1)]);
+    }
+
+    #[test]
+    fn quartic() {
+        assert_roots("x^4 - 5 * x^2 + 4 = 0 ?", &[(-2.0, 0.0, 1), (-1.0, 0.0, 1), (1.0, 0.0, 1), (2.0, 0.0, 1)]);
+        assert_roots("x^4 - 10 * x^3 + 35 * x^2 - 50 * x + 24 = 0 ?",
+                     &[(1.0, 0.0, 1), (2.0, 0.0, 1), (3.0, 0.0, 1), (4.0, 0.0, 1)]);
+        assert_roots("(x - 1)^4 = 0 ?", &[(1.0, 0.0, 4)]);
+        assert_roots("x^4 + 1 = 0 ?", &[(-0.70710677, -0.70710677, 1), (-0.70710677, 0.70710677, 1),
+                                        (0.70710677, -0.70710677, 1), (0.70710677, 0.70710677, 1)]);
+    }
+
+    #[test]
+    fn headings_follow_the_roots_found() {
+        let heading = |input: &str| format!("{}", equation(input)).lines().find(|l| l.ends_with(":")).unwrap().to_string();
+
+        assert_eq!(heading("x^4 + 1 = 0 ?"), "The complex solutions are:");
+        assert_eq!(heading("x^2 + 1 = 0 ?"), "Discriminant is strictly negative, the two complex solutions are:");
+        assert_eq!(heading("x^3 - 1 = 0 ?"), "The real and complex solutions are:");
+        assert_eq!(heading("x^4 - 5 * x^2 + 4 = 0 ?"), "The solutions are:");
+    }
+
+    #[test]
+    fn degree_above_four_is_unsolvable() {
+        let equation = equation("x^5 + 1 = 0 ?");
+
+        assert_eq!(equation.degree(), 5);
+        assert!(match equation.solutions { Solutions::Unsolvable => true, _ => false });
+    }
+
+    #[test]
+    fn small_leading_coefficient_is_kept() {
+        assert_eq!(equation("0.00000001 * x^2 + x = 0 ?").degree(), 2);
+        assert_roots("0.00000001 * x^2 = 0.00000001 ?", &[(-1.0, 0.0, 1), (1.0, 0.0, 1)]);
+        assert_roots("0.00000001 * x^2 + 0.00000002 * x = 0 ?", &[(-2.0, 0.0, 1), (0.0, 0.0, 1)]);
+        assert!(matches!(equation("x = x + 0.00000001 ?").solutions, Solutions::NoSolution));
+    }
+
+    #[test]
+    fn cancelled_terms_are_dropped() {
+        assert_eq!(equation("0.1 * x^2 + 0.2 * x^2 - 0.3 * x^2 + x - 1 = 0 ?").degree(), 1);
+        assert_eq!(equation("(x + 1) * (x - 1) - x^2 = 0 ?").degree(), 0);
+    }
+
+    #[test]
+    fn invalid_equations() {
+        let solver = Solver::new();
+        let parse = |input: &str| match EquExprParser::new().parse(input) {
+            Ok(Input::Equation(left, right)) => (*left, *right),
+            _ => panic!("not an equation"),
+        };
+        let (left, right) = parse("x * y = 1 ?");
+
+        assert!(Equation::new(&solver, left, right).is_err());
+        let (left, right) = parse("1 / x = 1 ?");
+
+        assert!(Equation::new(&solver, left, right).is_err());
     }
 }
@@ -0,0 +1,83 @@
+use ast::Expr;
+use solver::Solver;
+use builtins::check_arity;
+use decomposition::{get_numeric, to_matrix};
+use linalg::{Numeric, c_abs, c_add, c_product, identity, invert_numeric};
+
+// Degree of the diagonal Pade approximant, accurate to f64 once the norm is at most 1 / 2.
+const PADE_DEGREE: usize = 6;
+
+fn scale(a: &Numeric, factor: f64) -> Numeric {
+    a.iter().map(|line| line.iter().map(|&(x, y)| (x * factor, y * factor)).collect()).collect()
+}
+
+fn add(a: &Numeric, b: &Numeric) -> Numeric {
+    a.iter().zip(b.iter()).map(|(x, y)| x.iter().zip(y.iter()).map(|(&p, &q)| c_add(p, q)).collect()).collect()
+}
+
+// Scaling and squaring: exp(M) = (exp(M / 2^s))^(2^s) with a Pade approximant of exp(M / 2^s).
+pub fn expm(solver: &Solver, args: Vec<Expr>) -> Result<Expr, String> {
+    check_arity("expm", &args, 1, 1)?;
+    let a = get_numeric(solver, "expm", args[0].clone(), true)?;
+    if a.iter().any(|line| line.iter().any(|&(x, y)| !x.is_finite() || !y.is_finite())) {
+        return Err(format!("expm: expected finite entries"));
+    }
+    let n = a.len();
+    let norm = a.iter().map(|line| line.iter().map(|&x| c_abs(x)).sum::<f64>()).fold(0.0, f64::max);
+    let squarings = if norm > 0.5 { (norm / 0.5).log2().ceil() as i32 } else { 0 };
+    let a = scale(&a, 0.5f64.powi(squarings));
+    let mut numerator = identity(n);
+    let mut denominator = identity(n);
+    let mut power = identity(n);
+    let mut coef = 1.0;
+
+    for k in 1..(PADE_DEGREE + 1) {
+        coef *= (PADE_DEGREE - k + 1) as f64 / (k * (2 * PADE_DEGREE - k + 1)) as f64;
+        power = c_product(&power, &a);
+        numerator = add(&numerator, &scale(&power, coef));
+        denominator = add(&denominator, &scale(&power, if k % 2 == 0 { coef } else { -coef }));
+    }
+    let mut res = match invert_numeric(denominator) {
+        Some(inverse) => c_product(&inverse, &numerator),
+        None => return Err(format!("expm: the Pade denominator is singular")),
+    };
+
+    for _ in 0..squarings {
+        res = c_product(&res, &res);
+    }
+    Ok(to_matrix(res))
+}
+
+#[cfg(test)]
+mod tests {
+    use ast::Expr;
+    use solver::Solver;
+
+    fn expm(input: &str) -> Vec<f32> {
+        match Solver::new().run(&format!("expm({}) = ?", input)).unwrap() {
+            Expr::Matrix(t) => t.data.into_iter().map(|e| match e { Expr::Number(a) => a, e => panic!("unexpected {}", e) }).collect(),
+            e => panic!("{}: unexpected {}", input, e),
+        }
+    }
+
+    fn assert_entries(found: Vec<f32>, expected: &[f32]) {
+        assert_eq!(found.len(), expected.len());
+        for (a, b) in found.iter().zip(expected.iter()) {
+            assert!((a - b).abs() < 1e-5, "{:?} != {:?}", found, expected);
+        }
+    }
+
+    #[test]
+    fn exponentials() {
+        assert_entries(expm("[[0,0];[0,0]]"), &[1.0, 0.0, 0.0, 1.0]);
+        assert_entries(expm("[[1,0];[0,2]]"), &[1f32.exp(), 0.0, 0.0, 2f32.exp()]);
+        // The generator of the rotations, exp(t * J) turning by t radians.
+        assert_entries(expm("[[0,(-1)];[1,0]]"), &[1f32.cos(), -1f32.sin(), 1f32.sin(), 1f32.cos()]);
+    }
+
+    #[test]
+    fn non_finite_entries() {
+        let err = Solver::new().run("expm([[exp(100),1];[1,2]]) = ?").unwrap_err();
+        assert_eq!(err, "expm: expected finite entries");
+    }
+}
@@ -0,0 +1,163 @@
+use ast::Expr;
+use linalg::{Complex, from_complex, to_complex};
+
+type Matrice = Vec<Vec<Box<Expr>>>;
+
+// Fast path of the products and element-wise operations on numeric matrices: their entries
+// are copied to a contiguous row-major buffer of floats, imaginary parts only kept when one
+// is non zero, and the result goes back to an Expr::Matrix.
+//
+// This is narrower than a dense storage: numeric matrices are still stored as Expr entries of a
+// Tensor, and every other operation works on those. The copy costs one pass over the entries
+// against the n^3 of a product; the solves and decompositions have their own numeric form,
+// linalg::Numeric.
+pub struct Buffer {
+    pub rows: usize,
+    pub cols: usize,
+    pub re: Vec<f64>,
+    pub im: Option<Vec<f64>>,
+}
+
+// out += sign * a * b, with a of size n x m and b of size m x p.
+fn gemm(a: &[f64], b: &[f64], out: &mut [f64], (n, m, p): (usize, usize, usize), sign: f64) {
+    for i in 0..n {
+        let line = &mut out[i * p..(i + 1) * p];
+
+        for k in 0..m {
+            let factor = sign * a[i * m + k];
+
+            if factor == 0.0 {
+                continue;
+            }
+            for (res, &value) in line.iter_mut().zip(b[k * p..(k + 1) * p].iter()) {
+                *res += factor * value;
+            }
+        }
+    }
+}
+
+impl Buffer {
+    // None as soon as an entry is symbolic.
+    pub fn from_matrice(matrice: &Matrice) -> Option<Buffer> {
+        let (rows, cols) = (matrice.len(), matrice[0].len());
+        let mut re = Vec::with_capacity(rows * cols);
+        let mut im = Vec::with_capacity(rows * cols);
+
+        for line in matrice.iter() {
+            for value in line.iter() {
+                let (a, b) = to_complex(value)?;
+
+                re.push(a);
+                im.push(b);
+            }
+        }
+        let im = if im.iter().all(|&b| b == 0.0) { None } else { Some(im) };
+
+        Some(Buffer { rows, cols, re, im })
+    }
+
+    pub fn get(&self, i: usize) -> Complex {
+        (self.re[i], self.im.as_ref().map_or(0.0, |im| im[i]))
+    }
+
+    pub fn into_expr(self) -> Expr {
+        let cols = self.cols;
+
+        Expr::matrix((0..self.rows).map(|y| (0..cols).map(|x| {
+            Box::new(match self.im {
+                Some(_) => from_complex(self.get(y * cols + x)),
+                None => Expr::Number(self.re[y * cols + x] as f32 + 0.0),
+            })
+        }).collect()).collect())
+    }
+
+    // Matrix product, None when the inner dimensions differ.
+    pub fn product(&self, other: &Buffer) -> Option<Buffer> {
+        if self.cols != other.rows {
+            return None;
+        }
+        let size = (self.rows, self.cols, other.cols);
+        let mut re = vec![0.0; self.rows * other.cols];
+        let im = match (&self.im, &other.im) {
+            (&None, &None) => None,
+            (a_im, b_im) => {
+                let mut im = vec![0.0; self.rows * other.cols];
+
+                if let Some(ref b_im) = *b_im {
+                    gemm(&self.re, b_im, &mut im, size, 1.0);
+                }
+                if let Some(ref a_im) = *a_im {
+                    gemm(a_im, &other.re, &mut im, size, 1.0);
+                }
+                if let (Some(a_im), Some(b_im)) = (a_im, b_im) {
+                    gemm(a_im, b_im, &mut re, size, -1.0);
+                }
+                Some(im)
+            },
+        };
+
+        gemm(&self.re, &other.re, &mut re, size, 1.0);
+        Some(Buffer { rows: self.rows, cols: other.cols, re, im })
+    }
+
+    // Cell by cell on the given shape, a dimension of size 1 being stretched.
+    // None as soon as op gives up on a pair of entries.
+    pub fn zip<F>(&self, other: &Buffer, (rows, cols): (usize, usize), op: F) -> Option<Buffer>
+        where F: Fn(Complex, Complex) -> Option<Complex> {
+        let index = |m: &Buffer, y: usize, x: usize| (y % m.rows) * m.cols + x % m.cols;
+        let mut re = Vec::with_capacity(rows * cols);
+        let mut im = Vec::with_capacity(rows * cols);
+
+        for y in 0..rows {
+            for x in 0..cols {
+                let (a, b) = op(self.get(index(self, y, x)), other.get(index(other, y, x)))?;
+
+                re.push(a);
+                im.push(b);
+            }
+        }
+        let im = if im.iter().all(|&b| b == 0.0) { None } else { Some(im) };
+
+        Some(Buffer { rows, cols, re, im })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solver::tests::run;
+
+    fn buffer(values: &[f32], cols: usize) -> Buffer {
+        let matrice = values.chunks(cols).map(|line| line.iter().map(|&a| Box::new(Expr::Number(a))).collect()).collect();
+
+        Buffer::from_matrice(&matrice).unwrap()
+    }
+
+    #[test]
+    fn conversions() {
+        let matrice = vec![vec![Box::new(Expr::Number(1.0)), Box::new(Expr::Complex(0.0, 2.0))]];
+        let a = Buffer::from_matrice(&matrice).unwrap();
+
+        assert_eq!((a.rows, a.cols, a.get(1)), (1, 2, (0.0, 2.0)));
+        assert!(buffer(&[1.0, 2.0], 2).im.is_none());
+        assert!(Buffer::from_matrice(&vec![vec![Box::new(Expr::Variable(format!("x")))]]).is_none());
+    }
+
+    #[test]
+    fn products_and_zips() {
+        let (a, b) = (buffer(&[1.0, 2.0, 3.0, 4.0], 2), buffer(&[5.0, 6.0], 1));
+
+        assert_eq!(a.product(&b).unwrap().re, vec![17.0, 39.0]);
+        assert!(b.product(&a).is_none());
+        assert_eq!(a.zip(&b, (2, 2), |x, y| Some((x.0 * y.0, 0.0))).unwrap().re, vec![5.0, 10.0, 18.0, 24.0]);
+        assert!(a.zip(&b, (2, 2), |x, _| if x.0 > 3.0 { None } else { Some(x) }).is_none());
+    }
+
+    #[test]
+    fn element_wise_division_and_remainder() {
+        assert_eq!(run("[[1,2];[3,4]] / [[2,4];[8,16]]").unwrap(), "[ [0.5, 0.5] ; [0.375, 0.25] ]");
+        assert!(run("[[1,2];[3,4]] / [[0,4];[8,16]]").unwrap_err().contains("Can't divide by 0"));
+        assert_eq!(run("[[7,9];[3,4]] % [[2,4];[2,3]]").unwrap(), "[ [1, 1] ; [1, 1] ]");
+        assert_eq!(run("[[a,9]] / [[2,3]]").unwrap(), "[ [0.5 * a, 3] ]");
+    }
+}
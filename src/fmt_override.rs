@@ -1,7 +1,14 @@
-use ast::{Expr, Opcode};
+use ast::{Expr, Opcode, Slice};
+use equation::{Equation, Solutions};
+use polynome::Polynome;
+use sparse::Sparse;
+use tensor::{Tensor, fmt_shape};
+use linalg::from_complex;
 use std::f32;
 use std::fmt;
 
+const SHOWN_ENTRIES: usize = 10;
+
 impl fmt::Debug for Opcode {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match *self {
@@ -35,12 +42,97 @@ impl fmt::Display for Opcode {
 impl fmt::Debug for Expr {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
-            Expr::Matrix(x) => write!(f, "[{}, {}]", x.len(), x[0].len()),
+            Expr::Vector(x) => write!(f, "[{}]", x.len()),
+            Expr::Matrix(t) => write!(f, "{}", fmt_shape(&t.shape)),
+            Expr::Sparse(s) => write!(f, "[{}, {}]", s.rows, s.cols),
             n => write!(f, "{}", n),
         }
     }
 }
   
+fn precedence(expr: &Expr) -> u8 {
+    match *expr {
+        Expr::Op(_, Opcode::Add, _) | Expr::Op(_, Opcode::Sub, _) => 1,
+        Expr::Op(_, Opcode::Pow, _) => 3,
+        Expr::Op(_, _, _) => 2,
+        Expr::Complex(_, b) if b != 0.0 => 1,
+        _ => 4,
+    }
+}
+
+fn fmt_operand(f: &mut fmt::Formatter, expr: &Expr, parenthesis: bool) -> fmt::Result {
+    if parenthesis {
+        write!(f, "({})", expr)
+    } else {
+        write!(f, "{}", expr)
+    }
+}
+
+impl fmt::Display for Slice {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Slice::Single(ref e) => write!(f, "{}", e),
+            Slice::Range(ref a, ref b) => {
+                if let Some(ref a) = *a {
+                    write!(f, "{}", a)?;
+                }
+                write!(f, ":")?;
+                if let Some(ref b) = *b {
+                    write!(f, "{}", b)?;
+                }
+                Ok(())
+            },
+        }
+    }
+}
+
+impl fmt::Display for Sparse {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "sparse [{}, {}], {} entries", self.rows, self.cols, self.entries())?;
+        for (index, (i, j, value)) in self.triplets().enumerate() {
+            if index == SHOWN_ENTRIES {
+                return write!(f, "\n  ...");
+            }
+            write!(f, "\n  [{}, {}] {}", i, j, from_complex(value))?;
+        }
+        Ok(())
+    }
+}
+
+// Nested like the literals, matrices being the innermost blocks.
+fn fmt_block(f: &mut fmt::Formatter, shape: &[usize], data: &[Expr]) -> fmt::Result {
+    let size = data.len() / shape[0];
+
+    write!(f, "[ ")?;
+    for index in 0..shape[0] {
+        let block = &data[index * size..(index + 1) * size];
+
+        if index > 0 {
+            write!(f, " ; ")?;
+        }
+        if shape.len() > 2 {
+            fmt_block(f, &shape[1..], block)?;
+        } else {
+            write!(f, "[")?;
+            for (zindex, z) in block.iter().enumerate() {
+                if zindex < block.len() - 1 {
+                    write!(f, "{}, ", z)?;
+                } else {
+                    write!(f, "{}", z)?;
+                }
+            }
+            write!(f, "]")?;
+        }
+    }
+    write!(f, " ]")
+}
+
+impl fmt::Display for Tensor {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt_block(f, &self.shape, &self.data)
+    }
+}
+
 impl fmt::Display for Expr {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
@@ -53,28 +145,159 @@ impl fmt::Display for Expr {
                 }
             },
             Expr::Imaginary => write!(f, "i"),
-            Expr::Matrix(x) => {
-                write!(f, "[ ")?;
-                for (index, y) in x.iter().enumerate() {
-                    write!(f, "[")?;
-                    for (zindex, z) in y.iter().enumerate() {
-                        if zindex < y.len() - 1 {
-                            write!(f, "{}, ", z)?;
-                        } else {
-                            write!(f, "{}", z)?;
-                        }
-                    }
-                    if index < x.len() - 1 {
-                        write!(f, "] ; ")?;
+            Expr::Vector(ref v) => {
+                write!(f, "[")?;
+                for (index, e) in v.iter().enumerate() {
+                    if index < v.len() - 1 {
+                        write!(f, "{}, ", e)?;
                     } else {
-                        write!(f, "]")?;
+                        write!(f, "{}", e)?;
                     }
                 }
-                write!(f, " ]")
+                write!(f, "]")
             },
+            Expr::Matrix(ref t) => write!(f, "{}", t),
+            Expr::Sparse(ref s) => write!(f, "{}", s),
             Expr::Variable(ref s) => write!(f, "{}", s),
+            Expr::Text(ref s) => write!(f, "\"{}\"", s),
             Expr::Function(ref s, ref e) => write!(f, "{}({})", s, *e),
-            Expr::Op(ref a, ref o, ref b) => write!(f, "{} {} {}", a, o, b)
+            Expr::List(ref v) => {
+                for (index, e) in v.iter().enumerate() {
+                    if index < v.len() - 1 {
+                        write!(f, "{}, ", e)?;
+                    } else {
+                        write!(f, "{}", e)?;
+                    }
+                }
+                Ok(())
+            },
+            Expr::Index(ref e, ref slices) => {
+                fmt_operand(f, e, precedence(e) < 4)?;
+                write!(f, "[")?;
+                for (index, slice) in slices.iter().enumerate() {
+                    if index < slices.len() - 1 {
+                        write!(f, "{}, ", slice)?;
+                    } else {
+                        write!(f, "{}", slice)?;
+                    }
+                }
+                write!(f, "]")
+            },
+            Expr::Op(ref a, ref o, ref b) => {
+                let level = precedence(self);
+                let left = match **a {
+                    Expr::Number(n) if level == 3 => n < 0.0,
+                    _ => precedence(a) < level || (level == 3 && precedence(a) == 3),
+                };
+                let right = match (*o, precedence(b)) {
+                    (Opcode::Pow, p) => p < level,
+                    (Opcode::Add, p) | (Opcode::Mul, p) => p < level,
+                    (_, p) => p <= level,
+                };
+
+                fmt_operand(f, a, left)?;
+                write!(f, " {} ", o)?;
+                fmt_operand(f, b, right)
+            }
+        }
+    }
+}
+
+fn fmt_term(f: &mut fmt::Formatter, coef: f32, degree: usize, unknown: &str) -> fmt::Result {
+    match (coef, degree) {
+        (c, 0) => write!(f, "{}", c),
+        (c, 1) if c == 1.0 => write!(f, "{}", unknown),
+        (c, 1) => write!(f, "{} * {}", c, unknown),
+        (c, d) if c == 1.0 => write!(f, "{}^{}", unknown, d),
+        (c, d) => write!(f, "{} * {}^{}", c, unknown, d),
+    }
+}
+
+impl fmt::Display for Equation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut first = true;
+
+        write!(f, "Reduced form: ")?;
+        for (degree, coef) in self.reduced.iter().enumerate().rev() {
+            let coef = *coef;
+
+            if coef == 0.0 && (degree > 0 || !first) {
+                continue;
+            }
+            if first && coef < 0.0 {
+                write!(f, "-")?;
+            } else if !first && coef < 0.0 {
+                write!(f, " - ")?;
+            } else if !first {
+                write!(f, " + ")?;
+            }
+            fmt_term(f, coef.abs(), degree, &self.unknown)?;
+            first = false;
+        }
+        writeln!(f, " = 0")?;
+        writeln!(f, "Polynomial degree: {}", self.degree())?;
+        if let Some(delta) = self.pattern.as_ref().and_then(|p| p.discriminant()) {
+            writeln!(f, "Discriminant: {}", delta)?;
+        }
+        match self.solutions {
+            Solutions::AllReals => write!(f, "Every real number is a solution."),
+            Solutions::NoSolution => write!(f, "There is no solution."),
+            Solutions::Unsolvable =>
+                write!(f, "The polynomial degree is strictly greater than 4, I can't solve."),
+            Solutions::Roots(ref roots) => {
+                let complex = roots.iter().filter(|&&(ref r, _)| match *r { Expr::Complex(_, _) => true, _ => false }).count();
+
+                match (roots.iter().map(|&(_, n)| n).sum::<usize>(), complex, roots.len() - complex) {
+                    (1, _, _) => write!(f, "The solution is:")?,
+                    (2, _, 0) => write!(f, "Discriminant is strictly negative, the two complex solutions are:")?,
+                    (_, _, 0) => write!(f, "The complex solutions are:")?,
+                    (_, 0, _) => write!(f, "The solutions are:")?,
+                    _ => write!(f, "The real and complex solutions are:")?,
+                };
+                for &(ref root, multiplicity) in roots.iter() {
+                    if multiplicity > 1 {
+                        write!(f, "\n{} (multiplicity {})", root, multiplicity)?;
+                    } else {
+                        write!(f, "\n{}", root)?;
+                    }
+                }
+                Ok(())
+            },
+        }
+    }
+}
+
+impl fmt::Display for Polynome {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let terms = self.sorted_terms();
+
+        if terms.is_empty() {
+            return write!(f, "0");
+        }
+        for (index, &(ref monome, coef)) in terms.iter().enumerate() {
+            match (index, coef < 0.0) {
+                (0, true) => write!(f, "-")?,
+                (0, false) => (),
+                (_, true) => write!(f, " - ")?,
+                (_, false) => write!(f, " + ")?,
+            };
+            if coef.abs() != 1.0 || monome.is_empty() {
+                write!(f, "{}", coef.abs())?;
+                if !monome.is_empty() {
+                    write!(f, " * ")?;
+                }
+            }
+            for (i, &(ref var, n)) in monome.iter().enumerate() {
+                if i > 0 {
+                    write!(f, " * ")?;
+                }
+                if n == 1 {
+                    write!(f, "{}", var)?;
+                } else {
+                    write!(f, "{}^{}", var, n)?;
+                }
+            }
         }
+        Ok(())
     }
 }
@@ -0,0 +1,223 @@
+use ast::{Expr, Slice};
+use tensor::{Tensor, fmt_shape, position};
+use sparse::Sparse;
+use linalg::from_complex;
+
+// Negative indices count from the end.
+fn integer(expr: &Expr, size: usize) -> Result<isize, String> {
+    match *expr {
+        Expr::Number(a) if a == a.round() && a < 0.0 => Ok(a as isize + size as isize),
+        Expr::Number(a) if a == a.round() => Ok(a as isize),
+        ref e => Err(format!("expected an integer index, found '{}'", e)),
+    }
+}
+
+fn bound(expr: &Option<Box<Expr>>, size: usize, default: usize) -> Result<usize, String> {
+    match *expr {
+        Some(ref e) => Ok(integer(e, size)?.max(0).min(size as isize) as usize),
+        None => Ok(default),
+    }
+}
+
+// Positions selected along a dimension of the given size, and whether it is a range.
+fn positions(slice: &Slice, size: usize) -> Result<(Vec<usize>, bool), String> {
+    match *slice {
+        Slice::Single(ref e) => match integer(e, size)? {
+            i if i < 0 || i >= size as isize => Err(format!("index {} out of bounds for a dimension of size {}", e, size)),
+            i => Ok((vec![i as usize], false)),
+        },
+        Slice::Range(ref a, ref b) => {
+            let (start, end) = (bound(a, size, 0)?, bound(b, size, size)?);
+
+            if start >= end {
+                Err(format!("empty slice {}", slice))
+            } else {
+                Ok(((start..end).collect(), true))
+            }
+        },
+    }
+}
+
+// Selected rows and columns, a single index on a vector running along it.
+fn selection((n, m): (usize, usize), slices: &[Slice]) -> Result<((Vec<usize>, bool), (Vec<usize>, bool)), String> {
+    match *slices {
+        [ref y, ref x] => Ok((positions(y, n)?, positions(x, m)?)),
+        [ref x] if n == 1 => Ok(((vec![0], false), positions(x, m)?)),
+        [ref y] if m == 1 => Ok((positions(y, n)?, (vec![0], false))),
+        [ref y] => Ok((positions(y, n)?, ((0..m).collect(), true))),
+        _ => Err(format!("expected 1 or 2 indices, found {}", slices.len())),
+    }
+}
+
+// Selected positions on every axis of a tensor, and the offsets of the selected entries.
+fn tensor_selection(tensor: &Tensor, slices: &[Slice]) -> Result<(Vec<usize>, Vec<usize>), String> {
+    if slices.len() != tensor.shape.len() {
+        return Err(format!("expected {} indices, found {}", tensor.shape.len(), slices.len()));
+    }
+    let selected = slices.iter().zip(tensor.shape.iter()).map(|(s, &n)| positions(s, n)).collect::<Result<Vec<_>, _>>()?;
+    let shape: Vec<usize> = selected.iter().filter(|&&(_, range)| range).map(|&(ref p, _)| p.len()).collect();
+    let counts: Vec<usize> = selected.iter().map(|&(ref p, _)| p.len()).collect();
+    let strides = tensor.strides();
+    let offsets = (0..counts.iter().product()).map(|flat| {
+        position(&counts, flat).iter().enumerate().map(|(k, &i)| selected[k].0[i] * strides[k]).sum()
+    }).collect();
+
+    Ok((shape, offsets))
+}
+
+pub fn index(target: Expr, slices: Vec<Slice>) -> Result<Expr, String> {
+    let matrice = match target {
+        Expr::Matrix(t) if t.shape.len() == 2 => t.into_rows()?,
+        e @ Expr::Matrix(_) | e @ Expr::Vector(_) => {
+            let tensor = Tensor::from_expr(e)?;
+            let (shape, offsets) = tensor_selection(&tensor, &slices)?;
+
+            return Ok(Tensor { shape, data: offsets.iter().map(|&i| tensor.data[i].clone()).collect() }.into_expr());
+        },
+        Expr::Sparse(s) => return index_sparse(&s, slices),
+        Expr::List(list) => return index_list(list, slices),
+        e @ Expr::Variable(_) | e @ Expr::Function(_, _) => return Ok(Expr::Index(Box::new(e), slices)),
+        e => return Err(format!("can't index '{}'", e)),
+    };
+    let ((rows, row_range), (columns, column_range)) = selection((matrice.len(), matrice[0].len()), &slices)?;
+
+    if !row_range && !column_range {
+        return Ok(*matrice[rows[0]][columns[0]].clone());
+    }
+    Ok(Expr::matrix(rows.iter().map(|&y| columns.iter().map(|&x| matrice[y][x].clone()).collect()).collect()))
+}
+
+// A single entry, or a sparse block since positions are contiguous.
+fn index_sparse(s: &Sparse, slices: Vec<Slice>) -> Result<Expr, String> {
+    let ((rows, row_range), (columns, column_range)) = selection((s.rows, s.cols), &slices)?;
+
+    if !row_range && !column_range {
+        return Ok(from_complex(s.get(rows[0], columns[0])));
+    }
+    let (y, x) = (rows[0], columns[0]);
+    let entries = s.triplets().filter(|&(i, j, _)| i >= y && i < y + rows.len() && j >= x && j < x + columns.len());
+
+    Ok(Expr::Sparse(Sparse::from_triplets(rows.len(), columns.len(), entries.map(|(i, j, v)| (i - y, j - x, v)).collect())))
+}
+
+// Parts of a builtin result, a range giving a shorter list.
+fn index_list(list: Vec<Box<Expr>>, slices: Vec<Slice>) -> Result<Expr, String> {
+    if slices.len() != 1 {
+        return Err(format!("expected 1 index, found {}", slices.len()));
+    }
+    match positions(&slices[0], list.len())? {
+        (selected, true) => Ok(Expr::List(selected.iter().map(|&i| list[i].clone()).collect())),
+        (selected, false) => Ok(*list[selected[0]].clone()),
+    }
+}
+
+// Replaces the selected cells by value, a matrix of the same shape, a vector along a selected row
+// or column, or a single value.
+pub fn assign_index(target: Expr, slices: Vec<Slice>, value: Expr) -> Result<Expr, String> {
+    let mut matrice = match target {
+        Expr::Matrix(t) if t.shape.len() == 2 => t.into_rows()?,
+        e @ Expr::Matrix(_) | e @ Expr::Vector(_) => return assign_tensor(Tensor::from_expr(e)?, slices, value),
+        e => return Err(format!("can't index '{}'", e)),
+    };
+    let ((rows, _), (columns, _)) = selection((matrice.len(), matrice[0].len()), &slices)?;
+
+    match value {
+        Expr::Matrix(values) => {
+            let values = values.into_rows()?;
+
+            if values.len() != rows.len() || values[0].len() != columns.len() {
+                return Err(format!("invalid dimensions\n\texpected: [{}, {}]\n\tfound: [{}, {}]",
+                                   columns.len(), rows.len(), values[0].len(), values.len()));
+            }
+            for (i, &y) in rows.iter().enumerate() {
+                for (j, &x) in columns.iter().enumerate() {
+                    matrice[y][x] = values[i][j].clone();
+                }
+            }
+        },
+        value @ Expr::Vector(_) => {
+            let values = Tensor::from_expr(value)?;
+            let cells: Vec<(usize, usize)> = rows.iter().flat_map(|&y| columns.iter().map(move |&x| (y, x))).collect();
+
+            if (rows.len() != 1 && columns.len() != 1) || cells.len() != values.data.len() {
+                return Err(format!("invalid dimensions\n\texpected: {}\n\tfound: {}",
+                                   fmt_shape(&[rows.len(), columns.len()]), fmt_shape(&values.shape)));
+            }
+            for (&(y, x), e) in cells.iter().zip(values.data.into_iter()) {
+                matrice[y][x] = Box::new(e);
+            }
+        },
+        value => for &y in rows.iter() {
+            for &x in columns.iter() {
+                matrice[y][x] = Box::new(value.clone());
+            }
+        },
+    }
+    Ok(Expr::matrix(matrice))
+}
+
+// A single value fills the selection, otherwise the shapes must match once the axes of size 1 are dropped.
+fn assign_tensor(mut tensor: Tensor, slices: Vec<Slice>, value: Expr) -> Result<Expr, String> {
+    let (shape, offsets) = tensor_selection(&tensor, &slices)?;
+
+    match value {
+        value @ Expr::Matrix(_) | value @ Expr::Vector(_) => {
+            let value = Tensor::from_expr(value)?;
+            let squeeze = |shape: &[usize]| shape.iter().cloned().filter(|&n| n != 1).collect::<Vec<_>>();
+
+            if squeeze(&value.shape) != squeeze(&shape) {
+                return Err(format!("invalid dimensions\n\texpected: {}\n\tfound: {}", fmt_shape(&shape), fmt_shape(&value.shape)));
+            }
+            for (&i, e) in offsets.iter().zip(value.data.into_iter()) {
+                tensor.data[i] = e;
+            }
+        },
+        value => for &i in offsets.iter() {
+            tensor.data[i] = value.clone();
+        },
+    }
+    Ok(tensor.into_expr())
+}
+
+#[cfg(test)]
+mod tests {
+    use solver::Solver;
+    use solver::tests::run_in;
+
+    #[test]
+    fn reads_and_slices() {
+        let mut solver = Solver::new();
+
+        run_in(&mut solver, "A = [[1,2,3];[4,5,6];[7,8,9]]").unwrap();
+        assert_eq!(run_in(&mut solver, "A[1, 2] = ?").unwrap(), "6");
+        assert_eq!(run_in(&mut solver, "A[1, :] = ?").unwrap(), "[ [4, 5, 6] ]");
+        assert_eq!(run_in(&mut solver, "A[0:2, 1:3] = ?").unwrap(), "[ [2, 3] ; [5, 6] ]");
+    }
+
+    #[test]
+    fn assignments() {
+        let mut solver = Solver::new();
+
+        run_in(&mut solver, "A = [[1,2];[3,4]]").unwrap();
+        run_in(&mut solver, "A[1, 0] = 7").unwrap();
+        run_in(&mut solver, "A[0, :] = [[9, 9]]").unwrap();
+        assert_eq!(run_in(&mut solver, "A = ?").unwrap(), "[ [9, 9] ; [7, 4] ]");
+        assert!(run_in(&mut solver, "B[0] = 1").is_err());
+    }
+
+    #[test]
+    fn vector_assignments() {
+        let mut solver = Solver::new();
+
+        run_in(&mut solver, "A = [[1,2];[3,4]]").unwrap();
+        run_in(&mut solver, "A[0, :] = [9, 9]").unwrap();
+        assert_eq!(run_in(&mut solver, "A = ?").unwrap(), "[ [9, 9] ; [3, 4] ]");
+        run_in(&mut solver, "A[:, 1] = [5, 6]").unwrap();
+        assert_eq!(run_in(&mut solver, "A = ?").unwrap(), "[ [9, 5] ; [3, 6] ]");
+        assert!(run_in(&mut solver, "A[0, 0] = [1, 2]").unwrap_err().contains("invalid dimensions"));
+        assert!(run_in(&mut solver, "A[0, :] = [1, 2, 3]").unwrap_err().contains("invalid dimensions"));
+        assert!(run_in(&mut solver, "A[:, :] = [1, 2, 3, 4]").unwrap_err().contains("invalid dimensions"));
+        assert_eq!(run_in(&mut solver, "A = ?").unwrap(), "[ [9, 5] ; [3, 6] ]");
+        assert!(run_in(&mut solver, "2[0] = 1").unwrap_err().contains("can't assign"));
+    }
+}
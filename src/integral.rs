@@ -0,0 +1,254 @@
+use ast::{Expr, Opcode, Pow};
+use solver::Solver;
+use builtins::{check_arity, get_name, get_number};
+use derivative::depends;
+use polynome::Polynome;
+use simplify::{self, Term};
+
+const SAMPLES: usize = 16;
+
+fn function(name: &str, arg: Expr) -> Expr {
+    Expr::Function(name.to_string(), Box::new(arg))
+}
+
+fn no_antiderivative(expr: &Expr) -> String {
+    format!("integrate: no symbolic antiderivative found for '{}'", expr)
+}
+
+// Splits a * var + b, with a numeric, out of a linear expression.
+fn linear(expr: &Expr, var: &str) -> Option<(f32, Expr)> {
+    let polynome = Polynome::from_expr(expr)?;
+    let a = polynome.terms.get(&vec![(var.to_string(), 1)]).cloned()?;
+    let rest = polynome.sub(&Polynome::variable(var).scale(a));
+
+    if rest.variables().iter().any(|v| v == var) {
+        None
+    } else {
+        Some((a, rest.to_expr()))
+    }
+}
+
+// Antiderivative of expr ^ n where expr is a single factor.
+fn integrate_factor(expr: &Expr, n: f32, var: &str) -> Result<Expr, String> {
+    if let Some((a, _)) = linear(expr, var) {
+        return if n == -1.0 {
+            function("ln", function("abs", expr.clone())) / Expr::Number(a)
+        } else {
+            expr.clone().pow(Expr::Number(n + 1.0))? / Expr::Number((n + 1.0) * a)
+        };
+    }
+    match *expr {
+        Expr::Function(ref name, ref arg) if name == "exp" && n != 1.0 =>
+            integrate_factor(&function("exp", (Expr::Number(n) * *arg.clone())?), 1.0, var),
+        Expr::Function(ref name, ref arg) if n == 1.0 => match (name.as_str(), linear(arg, var)) {
+            ("sin", Some((a, _))) => (Expr::Number(-1.0) * function("cos", *arg.clone()))? / Expr::Number(a),
+            ("cos", Some((a, _))) => function("sin", *arg.clone()) / Expr::Number(a),
+            ("exp", Some((a, _))) => function("exp", *arg.clone()) / Expr::Number(a),
+            ("tan", Some((a, _))) =>
+                (Expr::Number(-1.0) * function("ln", function("abs", function("cos", *arg.clone()))))? / Expr::Number(a),
+            _ => Err(no_antiderivative(expr)),
+        },
+        Expr::Op(ref base, Opcode::Pow, ref exponent) if n == 1.0 && !depends(base, var) => match linear(exponent, var) {
+            Some((a, _)) => expr.clone() / (Expr::Number(a) * function("ln", *base.clone()))?,
+            None => Err(no_antiderivative(expr)),
+        },
+        _ => Err(no_antiderivative(expr)),
+    }
+}
+
+fn binomial(n: u32, k: u32) -> f32 {
+    (0..k).fold(1.0, |acc, i| acc * (n - i) as f32 / (i + 1) as f32)
+}
+
+// var ^ k * L ^ m with L = a * var + b, rewritten as a sum of powers of L.
+fn integrate_rational(k: u32, l: &Expr, m: f32, var: &str) -> Result<Expr, String> {
+    let (a, b) = match linear(l, var) {
+        Some(split) => split,
+        None => return Err(no_antiderivative(l)),
+    };
+    let mut res = Expr::Number(0.0);
+
+    for j in 0..(k + 1) {
+        let coef = ((Expr::Number(binomial(k, j)) * (Expr::Number(-1.0) * b.clone())?.pow(Expr::Number((k - j) as f32))?)?
+                    / Expr::Number(a.powi(k as i32)))?;
+
+        res = (res + (coef * integrate_factor(l, j as f32 + m, var)?)?)?;
+    }
+    Ok(res)
+}
+
+fn integrate_term(term: Term, var: &str) -> Result<Expr, String> {
+    let (dependent, constant): (Vec<_>, Vec<_>) = term.factors.into_iter().partition(|&(_, ref e, _)| depends(e, var));
+    let constant = simplify::to_expr(vec![Term { coef: term.coef, factors: constant }]);
+    let product = simplify::to_expr(vec![Term { coef: (1.0, 0.0), factors: dependent.clone() }]);
+    let is_var = |e: &Expr| match *e { Expr::Variable(ref s) => s.to_lowercase() == var, _ => false };
+    let integral = match dependent.len() {
+        0 => Expr::Variable(var.to_string()),
+        1 => integrate_factor(&dependent[0].1, dependent[0].2, var).map_err(|_| no_antiderivative(&product))?,
+        2 => {
+            let (x, l) = if is_var(&dependent[0].1) { (&dependent[0], &dependent[1]) } else { (&dependent[1], &dependent[0]) };
+
+            if !is_var(&x.1) || x.2 < 0.0 || x.2 != x.2.round() {
+                return Err(no_antiderivative(&product));
+            }
+            integrate_rational(x.2 as u32, &l.1, l.2, var).map_err(|_| no_antiderivative(&product))?
+        },
+        _ => return Err(no_antiderivative(&product)),
+    };
+
+    constant * integral
+}
+
+fn expand_in(solver: &Solver, expr: Expr, var: &str) -> Result<Expr, String> {
+    simplify::expand(solver.solve_function(expr, var.to_string(), Expr::Variable(var.to_string()))?)
+}
+
+pub fn antiderivative(solver: &Solver, expr: Expr, var: &str) -> Result<Expr, String> {
+    let mut res = Expr::Number(0.0);
+
+    for term in simplify::to_sum(expand_in(solver, expr, var)?)? {
+        res = (res + integrate_term(term, var)?)?;
+    }
+    solver.solve_function(res, var.to_string(), Expr::Variable(var.to_string()))
+}
+
+fn diverges(var: &str, pole: f32, a: f32, b: f32) -> String {
+    format!("integrate: the integral diverges, {} = {} is a pole in [{}, {}]", var, pole, a, b)
+}
+
+fn unproven(expr: &Expr, a: f32, b: f32) -> String {
+    format!("integrate: can't rule out a singularity of '{}' in [{}, {}]", expr, a, b)
+}
+
+// Fails when the zero of the linear expr lies in [a, b], or when expr is not linear.
+fn check_zero(expr: &Expr, var: &str, a: f32, b: f32) -> Result<(), String> {
+    match linear(expr, var) {
+        Some((slope, Expr::Number(c))) if 0.0 - c / slope >= a.min(b) && 0.0 - c / slope <= a.max(b) =>
+            Err(diverges(var, 0.0 - c / slope, a, b)),
+        Some((_, Expr::Number(_))) => Ok(()),
+        _ => Err(unproven(expr, a, b)),
+    }
+}
+
+// Only linear denominators and logarithms have singularities that can be located.
+fn check_factor(solver: &Solver, base: &Expr, n: f32, var: &str, a: f32, b: f32) -> Result<(), String> {
+    if !depends(base, var) {
+        return Ok(());
+    }
+    if n < 0.0 {
+        return check_zero(base, var, a, b);
+    }
+    match *base {
+        Expr::Variable(_) => Ok(()),
+        Expr::Function(ref name, box Expr::Function(ref abs, ref arg)) if name == "ln" && abs == "abs" =>
+            check_zero(arg, var, a, b),
+        Expr::Function(ref name, ref arg) if name == "ln" => check_zero(arg, var, a, b),
+        Expr::Function(ref name, ref arg) if ["sin", "cos", "exp", "sqrt", "abs"].contains(&name.as_str()) =>
+            check_continuity(solver, *arg.clone(), var, a, b),
+        Expr::Op(ref base, Opcode::Pow, ref exponent) => {
+            check_continuity(solver, *base.clone(), var, a, b)?;
+            check_continuity(solver, *exponent.clone(), var, a, b)
+        },
+        Expr::Op(_, Opcode::Add, _) | Expr::Op(_, Opcode::Sub, _) | Expr::Op(_, Opcode::Mul, _) =>
+            check_continuity(solver, base.clone(), var, a, b),
+        ref e => Err(unproven(e, a, b)),
+    }
+}
+
+fn check_continuity(solver: &Solver, expr: Expr, var: &str, a: f32, b: f32) -> Result<(), String> {
+    for term in simplify::to_sum(expand_in(solver, expr, var)?)? {
+        for (_, base, n) in term.factors {
+            check_factor(solver, &base, n, var, a, b)?;
+        }
+    }
+    Ok(())
+}
+
+// Evaluates the antiderivative across [a, b], a non finite value being a singularity missed above.
+fn check_samples(solver: &Solver, primitive: &Expr, var: &str, a: f32, b: f32) -> Result<(), String> {
+    for i in 0..(SAMPLES + 1) {
+        let x = a + (b - a) * i as f32 / SAMPLES as f32;
+
+        match solver.solve_function(primitive.clone(), var.to_string(), Expr::Number(x)) {
+            Ok(Expr::Number(y)) if y.is_finite() => (),
+            Ok(Expr::Complex(re, im)) if re.is_finite() && im.is_finite() => (),
+            _ => return Err(unproven(primitive, a, b)),
+        }
+    }
+    Ok(())
+}
+
+pub fn integrate(solver: &Solver, args: Vec<Expr>) -> Result<Expr, String> {
+    check_arity("integrate", &args, 2, 4)?;
+    if args.len() == 3 {
+        return Err(format!("integrate expects 2 or 4 arguments, found 3"));
+    }
+    let var = get_name(args[1].clone())?;
+    let primitive = antiderivative(solver, args[0].clone(), &var)?;
+
+    if args.len() == 2 {
+        return Ok(primitive);
+    }
+    let a = get_number(solver, args[2].clone())?;
+    let b = get_number(solver, args[3].clone())?;
+
+    check_continuity(solver, args[0].clone(), &var, a, b)?;
+    check_continuity(solver, primitive.clone(), &var, a, b)?;
+    check_samples(solver, &primitive, &var, a, b)?;
+
+    solver.solve_function(primitive.clone(), var.clone(), Expr::Number(b))?
+        - solver.solve_function(primitive, var, Expr::Number(a))?
+}
+
+#[cfg(test)]
+mod tests {
+    use ast::Expr;
+    use solver::Solver;
+    use solver::tests::run;
+
+    fn integrate(input: &str) -> Result<f32, String> {
+        match Solver::new().run(&format!("integrate({}) = ?", input))? {
+            Expr::Number(a) => Ok(a),
+            e => panic!("{}: unexpected {}", input, e),
+        }
+    }
+
+    #[test]
+    fn definite_integrals() {
+        assert!((integrate("x^2, x, 0, 1").unwrap() - 1.0 / 3.0).abs() < 1e-6);
+        assert!((integrate("1/x, x, 1, 2").unwrap() - 2f32.ln()).abs() < 1e-6);
+        assert!((integrate("1/(x - 2), x, 4, 3").unwrap() + 2f32.ln()).abs() < 1e-6);
+    }
+
+    #[test]
+    fn poles_in_the_interval_diverge() {
+        assert!(integrate("1/x, x, (-1), 1").unwrap_err().contains("diverges"));
+        assert!(integrate("1/x, x, 0, 1").unwrap_err().contains("diverges"));
+        assert!(integrate("1/(x - 1)^2, x, 3, 0").unwrap_err().contains("diverges"));
+        assert!(integrate("3/(x - 2) + x, x, 1, 4").unwrap_err().contains("diverges"));
+    }
+
+    #[test]
+    fn unlocated_singularities_are_refused() {
+        assert!(integrate("tan(x), x, 0, 3").unwrap_err().contains("singularity"));
+        assert!(integrate("tan(x), x, 0, 1").unwrap_err().contains("singularity"));
+        assert!((integrate("cos(x), x, 0, 3").unwrap() - 3f32.sin()).abs() < 1e-5);
+        assert!((integrate("exp(2 * x), x, 0, 1").unwrap() - (2f32.exp() - 1.0) / 2.0).abs() < 1e-5);
+    }
+
+    #[test]
+    fn antiderivatives() {
+        assert_eq!(run("integrate(x^2, x)").unwrap(), "0.33333334 * x ^ 3");
+        assert_eq!(run("integrate(1/(2*x+1), x)").unwrap(), "0.5 * ln(abs(2 * x + 1))");
+        assert_eq!(run("integrate(3 * x * (x + 1)^2, x)").unwrap(), "0.75 * x ^ 4 + 2 * x ^ 3 + 1.5 * x ^ 2");
+        assert_eq!(run("integrate(a * x + sin(2 * x), x)").unwrap(), "0.5 * a * x ^ 2 - 0.5 * cos(2 * x)");
+    }
+
+    #[test]
+    fn missing_antiderivatives() {
+        assert_eq!(run("integrate(sin(x)^2, x)").unwrap_err(), "integrate: no symbolic antiderivative found for 'sin(x) ^ 2'");
+        assert_eq!(run("integrate(exp(x^2), x)").unwrap_err(), "integrate: no symbolic antiderivative found for 'exp(x ^ 2)'");
+        assert_eq!(run("integrate(x * cos(x), x)").unwrap_err(), "integrate: no symbolic antiderivative found for 'cos(x) * x'");
+        assert_eq!(run("integrate(x, x, 1)").unwrap_err(), "integrate expects 2 or 4 arguments, found 3");
+    }
+}
@@ -0,0 +1,672 @@
+use ast::{Expr, Opcode};
+use solver::Solver;
+use builtins::{check_arity, get_matrix};
+use polynome::Polynome;
+use algebra::Rational;
+use sparse;
+use vector::{map_entries, to_row};
+use std::f32;
+
+// Largest denominator taken as an exact fraction by the elimination.
+const MAX_DENOMINATOR: i64 = 1000;
+
+pub type Rows = Vec<Vec<Expr>>;
+
+pub fn to_rows(matrice: Vec<Vec<Box<Expr>>>) -> Rows {
+    matrice.into_iter().map(|line| line.into_iter().map(|e| *e).collect()).collect()
+}
+
+pub fn from_rows(rows: Rows) -> Expr {
+    Expr::matrix(rows.into_iter().map(|line| line.into_iter().map(Box::new).collect()).collect())
+}
+
+pub type Complex = (f64, f64);
+pub type Numeric = Vec<Vec<Complex>>;
+
+pub fn c_add(a: Complex, b: Complex) -> Complex {
+    (a.0 + b.0, a.1 + b.1)
+}
+
+pub fn c_sub(a: Complex, b: Complex) -> Complex {
+    (a.0 - b.0, a.1 - b.1)
+}
+
+pub fn c_mul(a: Complex, b: Complex) -> Complex {
+    (a.0 * b.0 - a.1 * b.1, a.0 * b.1 + a.1 * b.0)
+}
+
+pub fn c_div(a: Complex, b: Complex) -> Complex {
+    let squared = b.0 * b.0 + b.1 * b.1;
+
+    ((a.0 * b.0 + a.1 * b.1) / squared, (a.1 * b.0 - a.0 * b.1) / squared)
+}
+
+pub fn c_abs(a: Complex) -> f64 {
+    (a.0 * a.0 + a.1 * a.1).sqrt()
+}
+
+pub fn c_conj(a: Complex) -> Complex {
+    (a.0, -a.1)
+}
+
+// Principal square root.
+pub fn c_sqrt(a: Complex) -> Complex {
+    let module = c_abs(a);
+    let re = ((module + a.0) / 2.0).max(0.0).sqrt();
+    let im = ((module - a.0) / 2.0).max(0.0).sqrt();
+
+    if a.1 < 0.0 { (re, -im) } else { (re, im) }
+}
+
+pub fn identity(n: usize) -> Numeric {
+    (0..n).map(|i| (0..n).map(|j| (if i == j { 1.0 } else { 0.0 }, 0.0)).collect()).collect()
+}
+
+pub fn identity_matrice(n: usize) -> Expr {
+    from_rows((0..n).map(|i| (0..n).map(|j| Expr::Number(if i == j { 1.0 } else { 0.0 })).collect()).collect())
+}
+
+pub fn c_product(a: &Numeric, b: &Numeric) -> Numeric {
+    (0..a.len()).map(|i| (0..b[0].len()).map(|j| {
+        (0..b.len()).fold((0.0, 0.0), |acc, k| c_add(acc, c_mul(a[i][k], b[k][j])))
+    }).collect()).collect()
+}
+
+// Modulus of a numeric entry, None for symbolic ones.
+pub fn modulus(expr: &Expr) -> Option<f32> {
+    match *expr {
+        Expr::Number(a) => Some(a.abs()),
+        Expr::Imaginary => Some(1.0),
+        Expr::Complex(a, b) => Some((a * a + b * b).sqrt()),
+        _ => None,
+    }
+}
+
+pub fn to_complex(expr: &Expr) -> Option<Complex> {
+    match *expr {
+        Expr::Number(a) => Some((a as f64, 0.0)),
+        Expr::Imaginary => Some((0.0, 1.0)),
+        Expr::Complex(a, b) => Some((a as f64, b as f64)),
+        _ => None,
+    }
+}
+
+pub fn from_complex(a: Complex) -> Expr {
+    if a.1.abs() <= f32::EPSILON as f64 * a.0.abs().max(1.0) {
+        Expr::Number(a.0 as f32 + 0.0)
+    } else {
+        Expr::Complex(a.0 as f32 + 0.0, a.1 as f32)
+    }
+}
+
+// None as soon as an entry is symbolic.
+pub fn to_numeric(rows: &Rows) -> Option<Numeric> {
+    rows.iter().map(|line| line.iter().map(to_complex).collect()).collect()
+}
+
+pub fn from_numeric(rows: Numeric) -> Rows {
+    rows.into_iter().map(|line| line.into_iter().map(from_complex).collect()).collect()
+}
+
+// Pivots below this are considered zero, relative to the largest entry.
+pub fn tolerance(rows: &Numeric) -> f64 {
+    let max = rows.iter().flat_map(|line| line.iter().map(|&a| c_abs(a))).fold(0.0, f64::max);
+
+    max * (rows.len().max(1) as f64) * 8.0 * f32::EPSILON as f64
+}
+
+// Row of the largest pivot candidate in column col, from row k down.
+pub fn partial_pivot(rows: &Numeric, k: usize, col: usize) -> usize {
+    (k..rows.len()).fold(k, |best, i| if c_abs(rows[i][col]) > c_abs(rows[best][col]) { i } else { best })
+}
+
+// Largest modulus among the numeric entries, the symbolic ones being skipped.
+fn numeric_norm(rows: &[Vec<Expr>]) -> f64 {
+    rows.iter().flat_map(|line| line.iter().filter_map(modulus)).fold(0.0, |max, a| max.max(a as f64))
+}
+
+// Numeric entries up to epsilon, symbolic ones when they reduce to the null polynomial.
+fn expr_is_zero(expr: &Expr, epsilon: f64) -> bool {
+    match modulus(expr) {
+        Some(a) => a as f64 <= epsilon,
+        None => Polynome::equivalent(expr, &Expr::Number(0.0)),
+    }
+}
+
+// Division known to be exact in Bareiss' algorithm, kept polynomial when possible.
+fn exact_div(a: Expr, b: &Expr) -> Result<Expr, String> {
+    if *b == Expr::Number(1.0) {
+        return Ok(normalize(a));
+    }
+    match (Polynome::from_expr(&a), Polynome::from_expr(b)) {
+        (Some(p), Some(q)) => match p.div_exact(&q) {
+            Some(res) => Ok(res.to_expr()),
+            None => a / b.clone(),
+        },
+        _ => a / b.clone(),
+    }
+}
+
+fn normalize(expr: Expr) -> Expr {
+    match Polynome::from_expr(&expr) {
+        Some(p) => p.to_expr(),
+        None => expr,
+    }
+}
+
+pub fn check_square(rows: &Rows) -> Result<usize, String> {
+    match (rows.len(), rows.first().map_or(0, |line| line.len())) {
+        (0, _) => Err(format!("empty matrice")),
+        (a, b) if a != b => Err(format!("non-square matrice [{}, {}]", a, b)),
+        (a, _) => Ok(a),
+    }
+}
+
+fn det_numeric(mut rows: Numeric) -> Expr {
+    let n = rows.len();
+    let epsilon = tolerance(&rows);
+    let mut det = (1.0, 0.0);
+
+    for k in 0..n {
+        let pivot = partial_pivot(&rows, k, k);
+
+        if c_abs(rows[pivot][k]) <= epsilon {
+            return Expr::Number(0.0);
+        }
+        if pivot != k {
+            rows.swap(pivot, k);
+            det = (-det.0, -det.1);
+        }
+        det = c_mul(det, rows[k][k]);
+        for i in (k + 1)..n {
+            let factor = c_div(rows[i][k], rows[k][k]);
+
+            for j in k..n {
+                rows[i][j] = c_sub(rows[i][j], c_mul(factor, rows[k][j]));
+            }
+        }
+    }
+    from_complex(det)
+}
+
+// Bareiss fraction-free elimination: every division is exact.
+fn det_bareiss(mut rows: Rows) -> Result<Expr, String> {
+    let n = rows.len();
+    let mut sign = 1.0;
+    let mut previous = Expr::Number(1.0);
+
+    for k in 0..(n - 1) {
+        // Relative to the remaining entries, which grow as minors of order k + 1.
+        let epsilon = numeric_norm(&rows[k..]) * n as f64 * 8.0 * f32::EPSILON as f64;
+
+        if expr_is_zero(&rows[k][k], epsilon) {
+            match ((k + 1)..n).find(|&i| !expr_is_zero(&rows[i][k], epsilon)) {
+                Some(i) => {
+                    rows.swap(i, k);
+                    sign = -sign;
+                },
+                None => return Ok(Expr::Number(0.0)),
+            }
+        }
+        for i in (k + 1)..n {
+            for j in (k + 1)..n {
+                let value = ((rows[i][j].clone() * rows[k][k].clone())?
+                             - (rows[i][k].clone() * rows[k][j].clone())?)?;
+
+                rows[i][j] = exact_div(value, &previous)?;
+            }
+        }
+        previous = rows[k][k].clone();
+    }
+    Ok(normalize((Expr::Number(sign) * rows[n - 1][n - 1].clone())?))
+}
+
+pub fn determinant(rows: Rows) -> Result<Expr, String> {
+    check_square(&rows)?;
+    match to_numeric(&rows) {
+        Some(numeric) => Ok(det_numeric(numeric)),
+        None => det_bareiss(rows),
+    }
+}
+
+// Gauss-Jordan elimination on [M | I].
+pub fn invert_numeric(mut rows: Numeric) -> Option<Numeric> {
+    let n = rows.len();
+    let epsilon = tolerance(&rows);
+    let mut inverse = identity(n);
+
+    for k in 0..n {
+        let pivot = partial_pivot(&rows, k, k);
+
+        if c_abs(rows[pivot][k]) <= epsilon {
+            return None;
+        }
+        rows.swap(pivot, k);
+        inverse.swap(pivot, k);
+        let value = rows[k][k];
+
+        for j in 0..n {
+            rows[k][j] = c_div(rows[k][j], value);
+            inverse[k][j] = c_div(inverse[k][j], value);
+        }
+        for i in (0..n).filter(|&i| i != k) {
+            let factor = rows[i][k];
+
+            for j in 0..n {
+                rows[i][j] = c_sub(rows[i][j], c_mul(factor, rows[k][j]));
+                inverse[i][j] = c_sub(inverse[i][j], c_mul(factor, inverse[k][j]));
+            }
+        }
+    }
+    Some(inverse)
+}
+
+fn minor(rows: &Rows, y: usize, x: usize) -> Rows {
+    rows.iter().enumerate().filter(|&(i, _)| i != y)
+        .map(|(_, line)| line.iter().enumerate().filter(|&(j, _)| j != x).map(|(_, e)| e.clone()).collect())
+        .collect()
+}
+
+// Adjugate over the determinant, each cofactor computed fraction-free.
+fn invert_symbolic(rows: Rows) -> Result<Rows, String> {
+    let n = rows.len();
+    let det = det_bareiss(rows.clone())?;
+
+    if expr_is_zero(&det, numeric_norm(&rows).powi(n as i32) * n as f64 * 8.0 * f32::EPSILON as f64) {
+        return Err(format!("det({}) == 0", from_rows(rows)));
+    }
+    if n == 1 {
+        return Ok(vec![vec![(Expr::Number(1.0) / det)?]]);
+    }
+    let mut inverse = Vec::with_capacity(n);
+
+    for i in 0..n {
+        let mut line = Vec::with_capacity(n);
+
+        for j in 0..n {
+            let sign = if (i + j) % 2 == 0 { 1.0 } else { -1.0 };
+            let cofactor = (Expr::Number(sign) * det_bareiss(minor(&rows, j, i))?)?;
+
+            line.push(exact_div(cofactor, &det)?);
+        }
+        inverse.push(line);
+    }
+    Ok(inverse)
+}
+
+pub fn invert(rows: Rows) -> Result<Rows, String> {
+    check_square(&rows)?;
+    match to_numeric(&rows) {
+        Some(numeric) => match invert_numeric(numeric) {
+            Some(inverse) => Ok(from_numeric(inverse)),
+            None => Err(format!("det({}) == 0", from_rows(rows))),
+        },
+        None => invert_symbolic(rows),
+    }
+}
+
+pub fn det(solver: &Solver, args: Vec<Expr>) -> Result<Expr, String> {
+    check_arity("det", &args, 1, 1)?;
+    match solver.eval(args[0].clone())? {
+        Expr::Sparse(a) => {
+            sparse::check_square("det", &a)?;
+            Ok(from_complex(a.determinant()))
+        },
+        a => determinant(to_rows(get_matrix(solver, a)?)),
+    }
+}
+
+pub fn inv(solver: &Solver, args: Vec<Expr>) -> Result<Expr, String> {
+    check_arity("inv", &args, 1, 1)?;
+    match invert(to_rows(get_matrix(solver, args[0].clone())?)) {
+        Ok(rows) => Ok(from_rows(rows)),
+        Err(s) => Err(format!("can't invert: {}", s)),
+    }
+}
+
+// Gauss-Jordan elimination in place, returns the pivot columns.
+pub fn reduce_rows(rows: &mut Numeric) -> Vec<usize> {
+    let (n, m) = (rows.len(), rows[0].len());
+    let epsilon = tolerance(rows);
+    let mut pivots = Vec::new();
+
+    for col in 0..m {
+        let k = pivots.len();
+
+        if k == n {
+            break;
+        }
+        let pivot = partial_pivot(rows, k, col);
+
+        if c_abs(rows[pivot][col]) <= epsilon {
+            for i in k..n {
+                rows[i][col] = (0.0, 0.0);
+            }
+            continue;
+        }
+        rows.swap(pivot, k);
+        let value = rows[k][col];
+
+        for j in 0..m {
+            rows[k][j] = c_div(rows[k][j], value);
+        }
+        for i in (0..n).filter(|&i| i != k) {
+            let factor = rows[i][col];
+
+            for j in 0..m {
+                rows[i][j] = c_sub(rows[i][j], c_mul(factor, rows[k][j]));
+            }
+            rows[i][col] = (0.0, 0.0);
+        }
+        pivots.push(col);
+    }
+    pivots
+}
+
+// Integers and fractions as typed, giving back the same float, other decimals are left to the numeric path.
+fn to_rational(rows: &Rows) -> Option<Vec<Vec<Rational>>> {
+    rows.iter().map(|line| line.iter().map(|e| match *e {
+        Expr::Number(a) => Rational::from_f32(a).ok().filter(|r| r.den <= MAX_DENOMINATOR && r.to_f32() == a),
+        _ => None,
+    }).collect()).collect()
+}
+
+// Exact Gauss-Jordan elimination in place, returns the pivot columns.
+fn reduce_rational(rows: &mut Vec<Vec<Rational>>) -> Result<Vec<usize>, String> {
+    let (n, m) = (rows.len(), rows[0].len());
+    let mut pivots = Vec::new();
+
+    for col in 0..m {
+        let k = pivots.len();
+
+        if k == n {
+            break;
+        }
+        let pivot = match (k..n).find(|&i| !rows[i][col].is_zero()) {
+            Some(pivot) => pivot,
+            None => continue,
+        };
+        rows.swap(pivot, k);
+        let value = rows[k][col];
+
+        for j in 0..m {
+            rows[k][j] = rows[k][j].div(value)?;
+        }
+        for i in (0..n).filter(|&i| i != k) {
+            let factor = rows[i][col];
+
+            for j in 0..m {
+                rows[i][j] = rows[i][j].sub(factor.mul(rows[k][j])?)?;
+            }
+        }
+        pivots.push(col);
+    }
+    Ok(pivots)
+}
+
+// Reduced row echelon form and pivot columns, exact when every entry is rational
+// and the elimination doesn't overflow.
+pub fn echelon(name: &str, rows: &Rows) -> Result<(Numeric, Vec<usize>), String> {
+    if let Some(mut exact) = to_rational(rows) {
+        if let Ok(pivots) = reduce_rational(&mut exact) {
+            let numeric = exact.into_iter().map(|line| line.into_iter().map(|a| (a.num as f64 / a.den as f64, 0.0)).collect());
+
+            return Ok((numeric.collect(), pivots));
+        }
+    }
+    match to_numeric(rows) {
+        Some(mut numeric) => {
+            let pivots = reduce_rows(&mut numeric);
+
+            Ok((numeric, pivots))
+        },
+        None => Err(format!("{}: expected numeric entries", name)),
+    }
+}
+
+// One vector per free column of a reduced matrix of width m.
+pub fn kernel_basis(rows: &Numeric, pivots: &[usize], m: usize) -> Vec<Vec<Complex>> {
+    (0..m).filter(|j| !pivots.contains(j)).map(|free| {
+        let mut vector = vec![(0.0, 0.0); m];
+
+        vector[free] = (1.0, 0.0);
+        for (i, &col) in pivots.iter().enumerate() {
+            vector[col] = (-rows[i][free].0, -rows[i][free].1);
+        }
+        vector
+    }).collect()
+}
+
+pub fn column(vector: Vec<Complex>) -> Expr {
+    from_rows(vector.into_iter().map(|a| vec![from_complex(a)]).collect())
+}
+
+// The unique solution, or a particular solution and the kernel basis as the columns of a matrix.
+pub fn linsolve(solver: &Solver, args: Vec<Expr>) -> Result<Expr, String> {
+    check_arity("linsolve", &args, 2, 2)?;
+    let a = match solver.eval(args[0].clone())? {
+        Expr::Sparse(a) => return sparse::linsolve(solver, &a, args[1].clone()),
+        a => to_rows(get_matrix(solver, a)?),
+    };
+    let mut b = to_rows(get_matrix(solver, args[1].clone())?);
+    let (n, m) = (a.len(), a[0].len());
+    let b = match (b.len(), b[0].len()) {
+        (y, 1) if y == n => b.into_iter().map(|mut line| line.remove(0)).collect(),
+        (1, x) if x == n => b.remove(0),
+        (y, x) => return Err(format!("linsolve: expected a vector of size {}, found [{}, {}]", n, y, x)),
+    };
+    let augmented: Rows = a.into_iter().zip(b).map(|(mut line, value)| { line.push(value); line }).collect();
+    let (rows, pivots) = echelon("linsolve", &augmented)?;
+
+    if pivots.last() == Some(&m) {
+        return Err(format!("linsolve: the system has no solution"));
+    }
+    let mut particular = vec![(0.0, 0.0); m];
+
+    for (i, &col) in pivots.iter().enumerate() {
+        particular[col] = rows[i][m];
+    }
+    let kernel = kernel_basis(&rows, &pivots, m);
+
+    if kernel.is_empty() {
+        return Ok(column(particular));
+    }
+    let vectors = kernel.into_iter().map(|v| v.into_iter().map(from_complex).collect());
+
+    Ok(Expr::List(vec![Box::new(column(particular)), Box::new(basis(vectors.collect(), m))]))
+}
+
+pub fn rref(solver: &Solver, args: Vec<Expr>) -> Result<Expr, String> {
+    check_arity("rref", &args, 1, 1)?;
+    let (rows, _) = echelon("rref", &to_rows(get_matrix(solver, args[0].clone())?))?;
+
+    Ok(from_rows(from_numeric(rows)))
+}
+
+pub fn rank(solver: &Solver, args: Vec<Expr>) -> Result<Expr, String> {
+    check_arity("rank", &args, 1, 1)?;
+    let (_, pivots) = echelon("rank", &to_rows(get_matrix(solver, args[0].clone())?))?;
+
+    Ok(Expr::Number(pivots.len() as f32))
+}
+
+// Basis vectors as the columns of a matrix, the zero vector for the trivial space.
+fn basis(vectors: Vec<Vec<Expr>>, size: usize) -> Expr {
+    if vectors.is_empty() {
+        return from_rows((0..size).map(|_| vec![Expr::Number(0.0)]).collect());
+    }
+    from_rows((0..size).map(|i| vectors.iter().map(|v| v[i].clone()).collect()).collect())
+}
+
+pub fn kernel(solver: &Solver, args: Vec<Expr>) -> Result<Expr, String> {
+    check_arity("kernel", &args, 1, 1)?;
+    let matrice = to_rows(get_matrix(solver, args[0].clone())?);
+    let m = matrice[0].len();
+    let (rows, pivots) = echelon("kernel", &matrice)?;
+    let vectors = kernel_basis(&rows, &pivots, m).into_iter().map(|v| v.into_iter().map(from_complex).collect());
+
+    Ok(basis(vectors.collect(), m))
+}
+
+pub fn image(solver: &Solver, args: Vec<Expr>) -> Result<Expr, String> {
+    check_arity("image", &args, 1, 1)?;
+    let matrice = to_rows(get_matrix(solver, args[0].clone())?);
+    let n = matrice.len();
+    let (_, pivots) = echelon("image", &matrice)?;
+    let vectors = pivots.iter().map(|&col| matrice.iter().map(|line| line[col].clone()).collect());
+
+    Ok(basis(vectors.collect(), n))
+}
+
+// Complex conjugate, variables being taken as real.
+pub fn conjugate(expr: Expr) -> Result<Expr, String> {
+    match expr {
+        Expr::Imaginary => Ok(Expr::Complex(0.0, -1.0)),
+        Expr::Complex(a, b) => Ok(Expr::Complex(a, -b)),
+        Expr::Op(a, op, b) => match op {
+            Opcode::Add => conjugate(*a)? + conjugate(*b)?,
+            Opcode::Sub => conjugate(*a)? - conjugate(*b)?,
+            Opcode::Mul => conjugate(*a)? * conjugate(*b)?,
+            Opcode::Div => conjugate(*a)? / conjugate(*b)?,
+            op => Ok(Expr::Op(a, op, b)),
+        },
+        Expr::Matrix(t) => Ok(Expr::Matrix(t.map(conjugate)?)),
+        Expr::Sparse(s) => Ok(Expr::Sparse(s.map(c_conj))),
+        Expr::Vector(vector) => map_entries(vector, conjugate),
+        e => Ok(e),
+    }
+}
+
+fn transpose_expr(name: &str, expr: Expr) -> Result<Expr, String> {
+    match expr {
+        Expr::Matrix(t) => {
+            let axes: Vec<usize> = (0..t.shape.len()).rev().collect();
+
+            Ok(t.permute(&axes).into_expr())
+        },
+        Expr::Sparse(s) => Ok(Expr::Sparse(s.transpose())),
+        Expr::Vector(vector) => Ok(Expr::matrix(to_row(vector))),
+        e @ Expr::Number(_) | e @ Expr::Imaginary | e @ Expr::Complex(_, _) => Ok(e),
+        e => Ok(Expr::Function(name.to_string(), Box::new(e))),
+    }
+}
+
+pub fn transpose(solver: &Solver, args: Vec<Expr>) -> Result<Expr, String> {
+    check_arity("transpose", &args, 1, 1)?;
+    transpose_expr("transpose", solver.eval(args[0].clone())?)
+}
+
+pub fn ctranspose(solver: &Solver, args: Vec<Expr>) -> Result<Expr, String> {
+    check_arity("ctranspose", &args, 1, 1)?;
+    match solver.eval(args[0].clone())? {
+        e @ Expr::Matrix(_) | e @ Expr::Sparse(_) | e @ Expr::Vector(_) | e @ Expr::Number(_) | e @ Expr::Imaginary | e @ Expr::Complex(_, _) =>
+            transpose_expr("ctranspose", conjugate(e)?),
+        e => Ok(Expr::Function(format!("ctranspose"), Box::new(e))),
+    }
+}
+
+pub fn trace(solver: &Solver, args: Vec<Expr>) -> Result<Expr, String> {
+    check_arity("trace", &args, 1, 1)?;
+    if let Expr::Sparse(a) = solver.eval(args[0].clone())? {
+        let n = sparse::check_square("trace", &a)?;
+
+        return Ok(from_complex((0..n).fold((0.0, 0.0), |acc, i| c_add(acc, a.get(i, i)))));
+    }
+    let rows = to_rows(get_matrix(solver, args[0].clone())?);
+    let n = check_square(&rows)?;
+
+    rows.into_iter().enumerate().take(n).fold(Ok(Expr::Number(0.0)), |acc, (i, mut line)| acc? + line.swap_remove(i))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solver::tests::{run, run_in};
+
+    #[test]
+    fn determinants() {
+        assert_eq!(run("det([[1,2];[3,4]])").unwrap(), "-2");
+        assert_eq!(run("det([[2,0,0];[0,3,0];[0,0,4]])").unwrap(), "24");
+        assert_eq!(run("det([[i,1];[1,i]])").unwrap(), "-2");
+        assert_eq!(run("det([[1,2];[2,4]])").unwrap(), "0");
+        assert_eq!(run("det([[a,1];[0,2]])").unwrap(), "2 * a");
+        assert_eq!(run("det([[0.00000001,0];[0,a]])").unwrap(), "0.00000001 * a");
+        assert_eq!(run("det([[a,1];[2 * a,2]])").unwrap(), "0");
+    }
+
+    #[test]
+    fn inverses() {
+        assert_eq!(run("inv([[1,2];[3,4]])").unwrap(), "[ [-2, 1] ; [1.5, -0.5] ]");
+        assert_eq!(run("inv([[2,0];[0,4]]) ** [[2,0];[0,4]]").unwrap(), "[ [1, 0] ; [0, 1] ]");
+        assert!(run("inv([[1,2];[2,4]])").unwrap_err().contains("can't invert"));
+    }
+
+    #[test]
+    fn linear_systems() {
+        assert_eq!(run("linsolve([[1,2];[3,4]], [5,6])").unwrap(), "[ [-4] ; [4.5] ]");
+        assert_eq!(run("linsolve([[1,1,1];[2,2,2]], [1,2])").unwrap(), "[ [1] ; [0] ; [0] ], [ [-1, -1] ; [1, 0] ; [0, 1] ]");
+        assert_eq!(run("linsolve([[1,2];[2,4]], [1,3])"), Err(format!("linsolve: the system has no solution")));
+        assert!(run("linsolve([[1,2];[3,4]], [1,2,3])").unwrap_err().contains("expected a vector of size 2"));
+    }
+
+    fn numbers(rows: &[&[f32]]) -> Rows {
+        rows.iter().map(|line| line.iter().map(|&a| Expr::Number(a)).collect()).collect()
+    }
+
+    #[test]
+    fn echelon_forms() {
+        assert_eq!(run("rref([[1,2,3];[4,5,6];[7,8,10]])").unwrap(), "[ [1, 0, 0] ; [0, 1, 0] ; [0, 0, 1] ]");
+        assert_eq!(run("rref([[0.5,0.25];[1,0.5]])").unwrap(), "[ [1, 0.5] ; [0, 0] ]");
+        assert_eq!(run("rank([[1,1/3];[3,1]])").unwrap(), "1");
+        assert_eq!(run("kernel([[1,2];[2,4]])").unwrap(), "[ [-2] ; [1] ]");
+        assert_eq!(run("image([[1,2];[2,4]])").unwrap(), "[ [1] ; [2] ]");
+        assert_eq!(run("kernel([[1,0];[0,1]])").unwrap(), "[ [0] ; [0] ]");
+    }
+
+    #[test]
+    fn decimals_use_the_numeric_path() {
+        let identity = "[ [1, 0, 0, 0] ; [0, 1, 0, 0] ; [0, 0, 1, 0] ; [0, 0, 0, 1] ]";
+
+        assert!(to_rational(&numbers(&[&[0.1234, 0.5]])).is_none());
+        assert_eq!(run("rref([[0.1234,0.5678,0.9123,0.3141];[0.2718,0.1618,0.7071,0.5772];\
+                             [0.6931,0.4142,0.2236,0.8660];[0.1111,0.2222,0.3337,0.9999]])").unwrap(), identity);
+    }
+
+    #[test]
+    fn exact_overflow_falls_back() {
+        let rows = numbers(&[
+            &[987654.0, 123457.0, 555555.0, 777777.0],
+            &[314159.0, 271828.0, 161803.0, 141421.0],
+            &[999983.0, 999979.0, 999961.0, 999959.0],
+            &[104729.0, 104723.0, 104717.0, 104711.0],
+        ]);
+
+        assert!(reduce_rational(&mut to_rational(&rows).unwrap()).is_err());
+        assert_eq!(echelon("rank", &rows).unwrap().1, vec![0, 1, 2, 3]);
+    }
+
+    #[test]
+    fn shapes_are_checked() {
+        assert!(run("det([[1,2,3];[4,5,6]])").unwrap_err().contains("non-square"));
+        assert!(check_square(&Vec::new()).is_err());
+        assert_eq!(check_square(&vec![vec![Expr::Number(1.0)]]), Ok(1));
+    }
+
+    #[test]
+    fn transposes() {
+        let mut solver = Solver::new();
+
+        run_in(&mut solver, "A = [[1,i];[2,3 + 2 * i]]").unwrap();
+        assert_eq!(run_in(&mut solver, "A' = ?").unwrap(), "[ [1, 2] ; [i, 3 + 2i] ]");
+        assert_eq!(run_in(&mut solver, "A' = ?"), run_in(&mut solver, "transpose(A) = ?"));
+        assert_eq!(run_in(&mut solver, "ctranspose(A) = ?").unwrap(), "[ [1, 2] ; [0 -1i, 3 -2i] ]");
+        assert_eq!(run_in(&mut solver, "A'' = ?"), run_in(&mut solver, "A = ?"));
+        assert_eq!(run("[[1,2,3]]'").unwrap(), "[ [1] ; [2] ; [3] ]");
+    }
+
+    #[test]
+    fn traces() {
+        assert_eq!(run("trace([[1,i];[2,3 + 2 * i]])").unwrap(), "4 + 2i");
+        assert!(run("trace([[1,2,3];[4,5,6]])").unwrap_err().contains("non-square"));
+    }
+}
@@ -1,17 +1,15 @@
 #![feature(box_patterns)]
 
 extern crate rustyline;
+extern crate lalrpop_util;
 extern crate snowflake;
 
 mod parser;
 mod solver;
 mod ast;
 mod add_override;
-mod add_reduce;
 mod sub_override;
-mod sub_reduce;
 mod mul_override;
-mod mul_reduce;
 mod div_override;
 mod rem_override;
 mod cmp_override;
@@ -19,6 +17,30 @@ mod fmt_override;
 mod pow_trait;
 mod prod_trait;
 mod proddiv_trait;
+mod fast_path;
+mod sparse;
+mod tensor;
+mod vector;
+mod broadcast;
+mod indexing;
+mod equation;
+mod builtins;
+mod root_finding;
+mod simplify;
+mod polynome;
+mod algebra;
+mod elementary;
+mod derivative;
+mod integral;
+mod quadrature;
+mod linalg;
+mod decomposition;
+mod eigen;
+mod exponential;
+mod construct;
+mod reshape;
+mod reduction;
+mod utils;
 
 use solver::Solver;
 use ast::{Input, Expr};
@@ -32,6 +54,10 @@ fn parse(solver: &mut Solver, line: String) {
             Ok(expr) => println!("{}", expr),
             Err(err) => println!("{}", err),
         },
+        Ok(Input::Equation(left, right)) => match solver.solve_equation(*left, *right) {
+            Ok(equation) => println!("{}", equation),
+            Err(err) => println!("{}", err),
+        },
         Ok(Input::Eval(expr)) => match solver.eval(*expr) {
             Ok(expr) => println!("{}", expr),
             Err(err) => println!("{}", err),
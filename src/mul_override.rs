@@ -1,6 +1,11 @@
 use ast::{Expr, Opcode};
 use std::ops::Mul;
-use mul_reduce::{mul_reduce_simple};
+use simplify::simplify;
+use broadcast::numeric_matrix_matrix;
+use linalg::c_mul;
+use tensor::{element_wise, is_tensor};
+use vector;
+use sparse;
 
 fn mul_number_complex(n: f32, c: (f32, f32)) -> Result<Expr, String> {
     Ok(Expr::Complex(n * c.0, n * c.1))
@@ -43,37 +48,7 @@ fn mul_matrix_any(a: Vec<Vec<Box<Expr>>>, b: Expr) -> Result<Expr, String> {
         res.push(res_line);
     }
     if errors.is_empty() {
-        Ok(Expr::Matrix(res))
-    } else {
-        Err(errors)
-    }
-}
-
-fn mul_matrix_matrix(a: Vec<Vec<Box<Expr>>>, b: Vec<Vec<Box<Expr>>>) -> Result<Expr, String> {
-    let mut res = Vec::<Vec<Box<Expr>>>::new();
-    let mut errors = String::new();
-
-    for (y, line) in a.iter().zip(b.iter()).enumerate() {
-        let mut res_line = Vec::<Box<Expr>>::new();
-
-        for (x, value) in line.0.iter().zip(line.1.iter()).enumerate() {
-            let (left, right) = (*value.0.clone(), *value.1.clone());
-            res_line.push(match left * right {
-                Ok(a) => Box::new(a),
-                Err(s) => {
-                    errors = if errors.is_empty() {
-                        format!("{} at [{}, {}]", s, x, y)
-                    } else {
-                        format!("{}\n{} at [{}, {}]", errors, s, x, y)
-                    };
-                    Box::new(Expr::Number(0.0))
-                },
-            });
-        }
-        res.push(res_line);
-    }
-    if errors.is_empty() {
-        Ok(Expr::Matrix(res))
+        Ok(Expr::matrix(res))
     } else {
         Err(errors)
     }
@@ -84,6 +59,8 @@ impl Mul for Expr {
 
     fn mul(self, other: Expr) -> Result<Expr, String> {
         match (self, other) {
+            (a, b) if is_tensor(&a) || is_tensor(&b) => element_wise(a, b, |x, y| x * y),
+            (a @ Expr::Vector(_), b) | (a, b @ Expr::Vector(_)) => vector::element_wise(a, b, |x, y| x * y),
             (Expr::Number(a), Expr::Number(b)) => Ok(Expr::Number(a * b)),
             (Expr::Number(a), Expr::Complex(ca, cb)) | (Expr::Complex(ca, cb), Expr::Number(a)) =>
                 mul_number_complex(a, (ca, cb)),
@@ -93,15 +70,28 @@ impl Mul for Expr {
             (Expr::Complex(ca, cb), Expr::Imaginary) | (Expr::Imaginary, Expr::Complex(ca, cb)) =>
                 mul_complex_imaginary((ca, cb)),
             (Expr::Matrix(a), Expr::Number(b)) | (Expr::Number(b), Expr::Matrix(a)) =>
-                mul_matrix_any(a, Expr::Number(b)),
+                mul_matrix_any(a.into_rows()?, Expr::Number(b)),
             (Expr::Matrix(a), Expr::Complex(x, y)) | (Expr::Complex(x, y), Expr::Matrix(a)) =>
-                mul_matrix_any(a, Expr::Complex(x, y)),
+                mul_matrix_any(a.into_rows()?, Expr::Complex(x, y)),
             (Expr::Matrix(a), Expr::Imaginary) | (Expr::Imaginary, Expr::Matrix(a)) =>
-                mul_matrix_any(a, Expr::Imaginary),
-            (Expr::Matrix(a), Expr::Matrix(b)) => mul_matrix_matrix(a, b), //Kronecker product
-            (Expr::Op(xa, opa, ya), Expr::Op(xb, opb, yb)) => Err(format!("Can't reduce Op Op")),
-            (Expr::Op(a, op, b), c) | (c, Expr::Op(a, op, b)) => mul_reduce_simple(*a, *b, c, op),
-            (a, b) => Ok(Expr::Op(Box::new(a), Opcode::Mul, Box::new(b))),
+                mul_matrix_any(a.into_rows()?, Expr::Imaginary),
+            (Expr::Sparse(a), Expr::Sparse(b)) => {
+                a.check_shape((b.rows, b.cols))?;
+                Ok(Expr::Sparse(a.mul_entries(|i, j| b.get(i, j))))
+            },
+            (Expr::Sparse(a), Expr::Matrix(b)) | (Expr::Matrix(b), Expr::Sparse(a)) => match sparse::numeric(&b.into_rows()?) {
+                Some(b) => {
+                    a.check_shape((b.len(), b[0].len()))?;
+                    Ok(Expr::Sparse(a.mul_entries(|i, j| b[i][j])))
+                },
+                None => Err(sparse::unsupported("a product with symbolic entries")),
+            },
+            (Expr::Sparse(a), b) | (b, Expr::Sparse(a)) => match a.scale(&b) {
+                Some(s) => Ok(Expr::Sparse(s)),
+                None => Err(sparse::unsupported("a product with symbolic entries")),
+            },
+            (Expr::Matrix(a), Expr::Matrix(b)) => numeric_matrix_matrix(a.into_rows()?, b.into_rows()?, |x, y| Some(c_mul(x, y)), |x, y| x * y), //Kronecker product
+            (a, b) => simplify(Expr::Op(Box::new(a), Opcode::Mul, Box::new(b))),
         }
     }
 }
@@ -0,0 +1,293 @@
+use ast::{Expr, Opcode};
+use utils::{is_zero, cancel};
+use std::collections::BTreeMap;
+use std::cmp::Ordering;
+
+pub type Monome = Vec<(String, u32)>;
+
+#[derive(Clone)]
+pub struct Polynome {
+    pub terms: BTreeMap<Monome, f32>,
+}
+
+pub fn monome_degree(monome: &Monome) -> u32 {
+    monome.iter().map(|&(_, n)| n).sum()
+}
+
+fn monome_mul(a: &Monome, b: &Monome) -> Monome {
+    let mut res = a.clone();
+
+    for &(ref var, n) in b.iter() {
+        match res.iter().position(|&(ref v, _)| v == var) {
+            Some(index) => res[index].1 += n,
+            None => res.push((var.clone(), n)),
+        };
+    }
+    res.sort();
+    res
+}
+
+fn monome_div(a: &Monome, b: &Monome) -> Option<Monome> {
+    let mut res = a.clone();
+
+    for &(ref var, n) in b.iter() {
+        match res.iter().position(|&(ref v, m)| v == var && m >= n) {
+            Some(index) => res[index].1 -= n,
+            None => return None,
+        };
+    }
+    res.retain(|&(_, n)| n > 0);
+    Some(res)
+}
+
+// Graded lexicographic order: highest degree first, then by variable name.
+fn monome_cmp(a: &Monome, b: &Monome) -> Ordering {
+    monome_degree(b).cmp(&monome_degree(a)).then_with(|| {
+        for (x, y) in a.iter().zip(b.iter()) {
+            match x.0.cmp(&y.0).then(y.1.cmp(&x.1)) {
+                Ordering::Equal => continue,
+                o => return o,
+            }
+        }
+        b.len().cmp(&a.len())
+    })
+}
+
+impl Polynome {
+    pub fn constant(c: f32) -> Polynome {
+        let mut terms = BTreeMap::new();
+
+        if c != 0.0 {
+            terms.insert(vec![], c);
+        }
+        Polynome { terms: terms }
+    }
+
+    pub fn variable(name: &str) -> Polynome {
+        let mut terms = BTreeMap::new();
+
+        terms.insert(vec![(name.to_string(), 1)], 1.0);
+        Polynome { terms: terms }
+    }
+
+    pub fn monome(monome: Monome, coef: f32) -> Polynome {
+        let mut terms = BTreeMap::new();
+
+        terms.insert(monome, coef);
+        Polynome { terms: terms }
+    }
+
+    pub fn as_constant(&self) -> Option<f32> {
+        match self.terms.len() {
+            0 => Some(0.0),
+            1 => self.terms.get(&vec![]).cloned(),
+            _ => None,
+        }
+    }
+
+    pub fn variables(&self) -> Vec<String> {
+        let mut res: Vec<String> = self.terms.keys().flat_map(|m| m.iter().map(|&(ref v, _)| v.clone())).collect();
+
+        res.sort();
+        res.dedup();
+        res
+    }
+
+    pub fn degree(&self) -> u32 {
+        self.terms.keys().map(monome_degree).max().unwrap_or(0)
+    }
+
+    // Terms sorted from the highest degree down to the constant.
+    pub fn sorted_terms(&self) -> Vec<(Monome, f32)> {
+        let mut res: Vec<(Monome, f32)> = self.terms.iter().map(|(m, c)| (m.clone(), *c)).collect();
+
+        res.sort_by(|a, b| monome_cmp(&a.0, &b.0));
+        res
+    }
+
+    fn insert(&mut self, monome: Monome, coef: f32) {
+        let old = self.terms.get(&monome).cloned().unwrap_or(0.0);
+        let value = cancel(old + coef, old.abs().max(coef.abs()));
+
+        if value == 0.0 {
+            self.terms.remove(&monome);
+        } else {
+            self.terms.insert(monome, value);
+        }
+    }
+
+    pub fn add(&self, other: &Polynome) -> Polynome {
+        let mut res = self.clone();
+
+        for (monome, coef) in other.terms.iter() {
+            res.insert(monome.clone(), *coef);
+        }
+        res
+    }
+
+    pub fn scale(&self, n: f32) -> Polynome {
+        let mut res = Polynome::constant(0.0);
+
+        for (monome, coef) in self.terms.iter() {
+            res.insert(monome.clone(), coef * n);
+        }
+        res
+    }
+
+    pub fn sub(&self, other: &Polynome) -> Polynome {
+        self.add(&other.scale(-1.0))
+    }
+
+    pub fn mul(&self, other: &Polynome) -> Polynome {
+        let mut res = Polynome::constant(0.0);
+
+        for (ma, ca) in self.terms.iter() {
+            for (mb, cb) in other.terms.iter() {
+                res.insert(monome_mul(ma, mb), ca * cb);
+            }
+        }
+        res
+    }
+
+    pub fn pow(&self, n: u32) -> Polynome {
+        (0..n).fold(Polynome::constant(1.0), |acc, _| acc.mul(self))
+    }
+
+    pub fn leading(&self) -> Option<(Monome, f32)> {
+        self.sorted_terms().into_iter().next()
+    }
+
+    // Multivariate division, only successful when other divides self.
+    pub fn div_exact(&self, other: &Polynome) -> Option<Polynome> {
+        let (lead, coef) = other.leading()?;
+        let mut rest = self.clone();
+        let mut res = Polynome::constant(0.0);
+
+        while let Some((monome, c)) = rest.leading() {
+            let term = Polynome::monome(monome_div(&monome, &lead)?, c / coef);
+
+            res = res.add(&term);
+            rest = rest.sub(&term.mul(other));
+            rest.terms.remove(&monome);
+            if res.terms.len() > 1000 {
+                return None;
+            }
+        }
+        Some(res)
+    }
+
+    pub fn from_expr(expr: &Expr) -> Option<Polynome> {
+        match *expr {
+            Expr::Number(a) => Some(Polynome::constant(a)),
+            Expr::Variable(ref s) => Some(Polynome::variable(&s.to_lowercase())),
+            Expr::Op(ref a, op, ref b) => {
+                let (a, b) = (Polynome::from_expr(a)?, Polynome::from_expr(b)?);
+
+                match (op, b.as_constant()) {
+                    (Opcode::Add, _) => Some(a.add(&b)),
+                    (Opcode::Sub, _) => Some(a.sub(&b)),
+                    (Opcode::Mul, _) => Some(a.mul(&b)),
+                    (Opcode::Div, Some(n)) if n != 0.0 => Some(a.scale(1.0 / n)),
+                    (Opcode::Pow, Some(n)) if n >= 0.0 && is_zero(n - n.round()) => Some(a.pow(n.round() as u32)),
+                    _ => None,
+                }
+            },
+            _ => None,
+        }
+    }
+
+    // Equality of two expressions as polynomials, false when either is not one.
+    pub fn equivalent(a: &Expr, b: &Expr) -> bool {
+        match (Polynome::from_expr(a), Polynome::from_expr(b)) {
+            (Some(a), Some(b)) => a == b,
+            _ => false,
+        }
+    }
+
+    pub fn to_expr(&self) -> Expr {
+        let mut res: Option<Expr> = None;
+
+        for (monome, coef) in self.sorted_terms() {
+            let factors = monome.iter().map(|&(ref var, n)| match n {
+                1 => Expr::Variable(var.clone()),
+                n => Expr::Op(Box::new(Expr::Variable(var.clone())), Opcode::Pow, Box::new(Expr::Number(n as f32))),
+            }).fold(None, |acc, e| match acc {
+                Some(acc) => Some(Expr::Op(Box::new(acc), Opcode::Mul, Box::new(e))),
+                None => Some(e),
+            });
+            let term = |c: f32| match factors.clone() {
+                Some(e) if c == 1.0 => e,
+                Some(e) => Expr::Op(Box::new(Expr::Number(c)), Opcode::Mul, Box::new(e)),
+                None => Expr::Number(c),
+            };
+
+            res = Some(match res {
+                None => term(coef),
+                Some(acc) if coef < 0.0 => Expr::Op(Box::new(acc), Opcode::Sub, Box::new(term(-coef))),
+                Some(acc) => Expr::Op(Box::new(acc), Opcode::Add, Box::new(term(coef))),
+            });
+        }
+        res.unwrap_or(Expr::Number(0.0))
+    }
+}
+
+impl PartialEq for Polynome {
+    fn eq(&self, other: &Polynome) -> bool {
+        self.sub(other).terms.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solver::Solver;
+
+    fn body(solver: &mut Solver, line: &str, name: &str) -> Expr {
+        solver.run(line).unwrap();
+        solver.get_function(name).unwrap().1
+    }
+
+    #[test]
+    fn function_bodies_are_normalised() {
+        let mut solver = Solver::new();
+        let f = body(&mut solver, "f(x) = 2 * x + 3 * x^2 - x + 1", "f");
+
+        assert_eq!(format!("{}", Polynome::from_expr(&f).unwrap()), "3 * x^2 + x + 1");
+        let g = body(&mut solver, "g(x) = (x + 1) * (x - 1)", "g");
+
+        assert_eq!(format!("{}", Polynome::from_expr(&g).unwrap()), "x^2 - 1");
+    }
+
+    #[test]
+    fn small_coefficients_are_kept() {
+        let mut solver = Solver::new();
+        let g = body(&mut solver, "g(x) = 0.0000001 * x", "g");
+
+        assert!(Polynome::from_expr(&g).unwrap().degree() == 1);
+        assert!(match solver.run("g(1000000) = ?") { Ok(Expr::Number(a)) => (a - 0.1).abs() < 1e-6, _ => false });
+        let f = body(&mut solver, "f(x) = 0.0000001 * x^2 + x", "f");
+
+        assert_eq!(format!("{}", Polynome::from_expr(&f).unwrap()), "0.0000001 * x^2 + x");
+    }
+
+    #[test]
+    fn equivalence() {
+        let mut solver = Solver::new();
+        let f = body(&mut solver, "f(x) = (x + 1)^2", "f");
+        let g = body(&mut solver, "g(x) = x^2 + 2 * x + 1", "g");
+        let h = body(&mut solver, "h(x) = x^2 + 1", "h");
+
+        assert!(Polynome::equivalent(&f, &g));
+        assert!(!Polynome::equivalent(&f, &h));
+        assert!(!Polynome::equivalent(&f, &Expr::Imaginary));
+    }
+
+    #[test]
+    fn plain_equality_stays_structural() {
+        let sum = Expr::Op(Box::new(Expr::Variable(format!("x"))), Opcode::Add, Box::new(Expr::Number(1.0)));
+        let swapped = Expr::Op(Box::new(Expr::Number(1.0)), Opcode::Add, Box::new(Expr::Variable(format!("x"))));
+
+        assert!(Polynome::equivalent(&sum, &swapped));
+        assert!(sum != swapped);
+    }
+}
@@ -1,5 +1,10 @@
-use ast::{Pow, Expr, Opcode};
+use ast::{Pow, Prod, Expr, Opcode};
 use std::f32;
+use simplify::simplify;
+use linalg::{check_square, from_rows, identity_matrice, invert, to_rows};
+use tensor::{element_wise, is_tensor};
+use vector;
+use sparse;
 
 fn pow_complex_number(n: f32, c: Expr) -> Result<Expr, String> {
     let iterator = 0..(n as i32);
@@ -15,14 +20,80 @@ fn pow_complex_number(n: f32, c: Expr) -> Result<Expr, String> {
     Ok(res)
 }
 
+// Exponentiation by squaring, negative powers through the inverse.
+fn pow_matrice_number(mat: Vec<Vec<Box<Expr>>>, n: f32) -> Result<Expr, String> {
+    let rows = to_rows(mat);
+    let size = match check_square(&rows) {
+        Ok(size) => size,
+        Err(s) => return Err(format!("can't raise to a power: {}", s)),
+    };
+
+    if n != n.round() {
+        return Err(format!("can't raise a matrice to the non-integer power {}", n));
+    }
+    let mut base = match n < 0.0 {
+        true => match invert(rows) {
+            Ok(rows) => from_rows(rows),
+            Err(s) => return Err(format!("can't invert: {}", s)),
+        },
+        false => from_rows(rows),
+    };
+    let mut exponent = n.abs() as u64;
+    let mut res = identity_matrice(size);
+
+    while exponent > 0 {
+        if exponent & 1 == 1 {
+            res = res.prod(base.clone())?;
+        }
+        exponent >>= 1;
+        if exponent > 0 {
+            base = base.clone().prod(base)?;
+        }
+    }
+    Ok(res)
+}
+
 impl Pow for Expr {
     type Output = Result<Expr, String>;
 
     fn pow(self, other: Expr) -> Result<Expr, String> {
         match (self, other) {
+            (a, b) if is_tensor(&a) || is_tensor(&b) => element_wise(a, b, |x, y| x.pow(y)),
+            (a @ Expr::Vector(_), b) | (a, b @ Expr::Vector(_)) => vector::element_wise(a, b, |x, y| x.pow(y)),
             (Expr::Number(a), Expr::Number(b)) => Ok(Expr::Number(a.powf(b))),
             (Expr::Complex(ca, cb), Expr::Number(a)) => pow_complex_number(a, Expr::Complex(ca, cb)),
-            (a, b) => Ok(Expr::Op(Box::new(a), Opcode::Pow, Box::new(b))),
+            (Expr::Matrix(a), Expr::Number(b)) => pow_matrice_number(a.into_rows()?, b),
+            (Expr::Sparse(a), Expr::Number(n)) if n >= 0.0 && n == n.round() => match a.power(n as u64) {
+                Some(s) => Ok(Expr::Sparse(s)),
+                None => Err(format!("can't raise to a power: non-square matrice [{}, {}]", a.rows, a.cols)),
+            },
+            (Expr::Sparse(_), _) => Err(sparse::unsupported("this power")),
+            (a, b) => simplify(Expr::Op(Box::new(a), Opcode::Pow, Box::new(b))),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use solver::Solver;
+    use solver::tests::run_in;
+
+    #[test]
+    fn integer_matrix_powers() {
+        let mut solver = Solver::new();
+
+        run_in(&mut solver, "A = [[1,1];[0,1]]").unwrap();
+        assert_eq!(run_in(&mut solver, "A^0 = ?").unwrap(), "[ [1, 0] ; [0, 1] ]");
+        assert_eq!(run_in(&mut solver, "A^3 = ?").unwrap(), "[ [1, 3] ; [0, 1] ]");
+        assert_eq!(run_in(&mut solver, "A^(-1) = ?").unwrap(), "[ [1, -1] ; [0, 1] ]");
+        assert!(run_in(&mut solver, "A^0.5 = ?").unwrap_err().contains("non-integer"));
+    }
+
+    #[test]
+    fn invalid_bases() {
+        let mut solver = Solver::new();
+
+        assert!(run_in(&mut solver, "[[1,2];[2,4]]^(-1) = ?").unwrap_err().contains("can't invert"));
+        assert!(run_in(&mut solver, "[[1,2,3]]^2 = ?").unwrap_err().contains("non-square"));
+    }
+}
@@ -1,11 +1,22 @@
 use ast::{Prod, Expr, Opcode};
+use fast_path::Buffer;
+use sparse::{self, numeric};
+use decomposition::to_matrix;
+use vector::{self, to_column, to_row};
+use tensor::is_tensor;
 
 fn prod_matrice_matrice(a: Vec<Vec<Box<Expr>>>, b: Vec<Vec<Box<Expr>>>) -> Result<Expr, String> {
     let types: (usize, usize) = (a[0].len(), b.len());
     let (n, m, p) = match types {
         (m1, m2) if m1 == m2 => (a.len(), m1, b[0].len()),
-        _ => return Err(Expr::type_error(Expr::Matrix(a), Expr::Matrix(b), Opcode::Prod)),
+        _ => return Err(Expr::type_error(Expr::matrix(a), Expr::matrix(b), Opcode::Prod)),
     };
+
+    if let (Some(x), Some(y)) = (Buffer::from_matrice(&a), Buffer::from_matrice(&b)) {
+        if let Some(res) = x.product(&y) {
+            return Ok(res.into_expr());
+        }
+    }
     let mut res = Vec::<Vec<Box<Expr>>>::with_capacity(n);
 
     for i in 0..n {
@@ -21,7 +32,35 @@ fn prod_matrice_matrice(a: Vec<Vec<Box<Expr>>>, b: Vec<Vec<Box<Expr>>>) -> Resul
         }
         res.push(line);
     }
-    Ok(Expr::Matrix(res))
+    Ok(Expr::matrix(res))
+}
+
+// Stays sparse between sparse matrices, dense with a numeric dense matrice or vector.
+fn prod_sparse(a: Expr, b: Expr) -> Result<Expr, String> {
+    let res = match (&a, &b) {
+        (&Expr::Sparse(ref x), &Expr::Sparse(ref y)) => x.product(y).map(Expr::Sparse),
+        (&Expr::Sparse(ref x), &Expr::Matrix(ref y)) => match numeric(&y.clone().into_rows()?) {
+            Some(y) => x.product_dense(&y).map(to_matrix),
+            None => return Err(sparse::unsupported("a product with symbolic entries")),
+        },
+        (&Expr::Matrix(ref x), &Expr::Sparse(ref y)) => match numeric(&x.clone().into_rows()?) {
+            Some(x) => y.dense_product(&x).map(to_matrix),
+            None => return Err(sparse::unsupported("a product with symbolic entries")),
+        },
+        (&Expr::Sparse(_), &Expr::Vector(ref y)) => return a.prod(Expr::matrix(to_column(y.clone()))).map(vector_result),
+        (&Expr::Vector(ref x), &Expr::Sparse(_)) => return Expr::matrix(to_row(x.clone())).prod(b).map(vector_result),
+        _ => return Err(sparse::unsupported("this product")),
+    };
+
+    res.ok_or_else(|| Expr::type_error(a, b, Opcode::Prod))
+}
+
+// A matrix vector product gives back a vector.
+fn vector_result(expr: Expr) -> Expr {
+    match expr {
+        Expr::Matrix(t) => Expr::Vector(t.data),
+        e => e,
+    }
 }
 
 impl Prod for Expr {
@@ -29,7 +68,13 @@ impl Prod for Expr {
 
     fn prod(self, other: Expr) -> Result<Expr, String> {
         match (self, other) {
-            (Expr::Matrix(a), Expr::Matrix(b)) => prod_matrice_matrice(a, b),
+            (a, b) if is_tensor(&a) || is_tensor(&b) => Err(Expr::type_error(a, b, Opcode::Prod)),
+            (Expr::Matrix(a), Expr::Matrix(b)) => prod_matrice_matrice(a.into_rows()?, b.into_rows()?),
+            (a @ Expr::Sparse(_), b) | (a, b @ Expr::Sparse(_)) => prod_sparse(a, b),
+            (Expr::Vector(a), Expr::Vector(b)) if a.len() == b.len() => vector::product("**", a, b),
+            (a @ Expr::Vector(_), b @ Expr::Vector(_)) => Err(Expr::type_error(a, b, Opcode::Prod)),
+            (Expr::Matrix(a), Expr::Vector(b)) => prod_matrice_matrice(a.into_rows()?, to_column(b)).map(vector_result),
+            (Expr::Vector(a), Expr::Matrix(b)) => prod_matrice_matrice(to_row(a), b.into_rows()?).map(vector_result),
             (a, b) => Ok(Expr::Op(Box::new(a), Opcode::Prod, Box::new(b))),
         }
     }
@@ -1,19 +1,10 @@
 use ast::{Prod, ProdDiv, Expr, Opcode};
-use std::f32;
+use linalg::{from_rows, invert, to_rows};
+use sparse;
 
 fn matrice_invert(mat: Vec<Vec<Box<Expr>>>) -> Result<Expr, String> {
-    let invert = match (mat.len(), mat[0].len()) {
-        (a, b) if a != b =>
-            return Err(format!("non-square matrice [{}, {}]", a, b)),
-        (2, 2) => match ((*mat[0][0].clone() * *mat[1][1].clone())? - (*mat[0][1].clone() * *mat[1][0].clone())?)? { 
-            ref det if det >= &Expr::Number(0.0 - f32::EPSILON) && det <= &Expr::Number(0.0 + f32::EPSILON) =>
-                return Err(format!("det({}) == 0", Expr::Matrix(mat))),
-            det => (Expr::Number(1.0) / det)? * Expr::Matrix(mat) // Inverting 2x2 matrices
-        },
-        (a, b) => Err(format!("matrice of order [{}, {}]", a, b))
-    };
-    match invert {
-        Ok(a) => Ok(a),
+    match invert(to_rows(mat)) {
+        Ok(rows) => Ok(from_rows(rows)),
         Err(s) => Err(format!("can't invert: {}", s))
     }
 }    
@@ -23,7 +14,8 @@ impl ProdDiv for Expr {
 
     fn prod_div(self, other: Expr) -> Result<Expr, String> {
         match (self, other) {
-            (a, Expr::Matrix(b)) => a.prod(matrice_invert(b)?),
+            (a, Expr::Matrix(b)) => a.prod(matrice_invert(b.into_rows()?)?),
+            (_, Expr::Sparse(_)) => Err(sparse::unsupported("inverting")),
             (a, b) => Ok(Expr::Op(Box::new(a), Opcode::ProdDiv, Box::new(b))),
         }
     }
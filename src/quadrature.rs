@@ -0,0 +1,242 @@
+use ast::{Expr, Opcode};
+use solver::Solver;
+use builtins::{check_arity, get_name};
+use linalg::{Complex, c_abs, c_add, from_complex, to_complex};
+use std::f64;
+
+const MAX_INTERVALS: usize = 500;
+const TOLERANCE: f64 = 1e-6;
+
+// Gauss-Kronrod 7-15 nodes and weights, from QUADPACK.
+const XGK: [f64; 8] = [
+    0.9914553711208126, 0.9491079123427585,
+    0.8648644233597691, 0.7415311855993945,
+    0.5860872354676911, 0.4058451513773972,
+    0.20778495500789848, 0.0,
+];
+const WGK: [f64; 8] = [
+    0.022935322010529224, 0.06309209262997856,
+    0.10479001032225019, 0.14065325971552592,
+    0.1690047266392679, 0.19035057806478542,
+    0.20443294007529889, 0.20948214108472782,
+];
+const WG: [f64; 4] = [
+    0.1294849661688697, 0.27970539148927664,
+    0.3818300505051189, 0.4179591836734694,
+];
+
+enum Domain {
+    Finite(f64, f64),
+    Upper(f64),  // [a, inf)
+    Lower(f64),  // (-inf, b]
+    Whole,
+}
+
+struct Integrand<'a> {
+    solver: &'a Solver,
+    expr: Expr,
+    var: String,
+    domain: Domain,
+}
+
+impl<'a> Integrand<'a> {
+    // A division by zero at a node, as when it rounds to a pole, is a singularity too.
+    fn eval(&self, x: f64) -> Result<Complex, String> {
+        let diverges = || format!("nintegrate: the integral diverges, '{}' is not finite at {} = {}", self.expr, self.var, x as f32);
+        let y = match self.solver.solve_function(self.expr.clone(), self.var.clone(), Expr::Number(x as f32)) {
+            Err(ref err) if err == "Can't divide by 0" => return Err(diverges()),
+            res => res?,
+        };
+
+        match to_complex(&y) {
+            Some(y) if y.0.is_finite() && y.1.is_finite() => Ok(y),
+            Some(_) => Err(diverges()),
+            None => Err(format!("'{}' is not a number at {} = {}", y, self.var, x)),
+        }
+    }
+
+    // Integrand on the finite interval after the change of variable.
+    fn eval_mapped(&self, t: f64) -> Result<Complex, String> {
+        let (x, jacobian) = match self.domain {
+            Domain::Finite(_, _) => (t, 1.0),
+            Domain::Upper(a) => (a + t / (1.0 - t), 1.0 / ((1.0 - t) * (1.0 - t))),
+            Domain::Lower(b) => (b - (1.0 - t) / t, 1.0 / (t * t)),
+            Domain::Whole => (t / (1.0 - t * t), (1.0 + t * t) / ((1.0 - t * t) * (1.0 - t * t))),
+        };
+        let y = self.eval(x)?;
+
+        Ok((y.0 * jacobian, y.1 * jacobian))
+    }
+
+    fn bounds(&self) -> (f64, f64) {
+        match self.domain {
+            Domain::Finite(a, b) => (a, b),
+            Domain::Upper(_) | Domain::Lower(_) => (0.0, 1.0),
+            Domain::Whole => (-1.0, 1.0),
+        }
+    }
+}
+
+// (integral, error estimate) on [a, b].
+fn kronrod(f: &Integrand, a: f64, b: f64) -> Result<(Complex, f64), String> {
+    let (center, half) = ((a + b) / 2.0, (b - a) / 2.0);
+    let middle = f.eval_mapped(center)?;
+    let mut kronrod = (middle.0 * WGK[7], middle.1 * WGK[7]);
+    let mut gauss = (middle.0 * WG[3], middle.1 * WG[3]);
+
+    for i in 0..7 {
+        let left = f.eval_mapped(center - half * XGK[i])?;
+        let right = f.eval_mapped(center + half * XGK[i])?;
+        let sum = c_add(left, right);
+
+        kronrod = (kronrod.0 + WGK[i] * sum.0, kronrod.1 + WGK[i] * sum.1);
+        if i % 2 == 1 {
+            gauss = (gauss.0 + WG[i / 2] * sum.0, gauss.1 + WG[i / 2] * sum.1);
+        }
+    }
+    let integral = (kronrod.0 * half, kronrod.1 * half);
+    let error = c_abs(((kronrod.0 - gauss.0) * half, (kronrod.1 - gauss.1) * half));
+
+    Ok((integral, error))
+}
+
+// Global adaptive scheme: always bisect the interval with the largest error.
+fn adaptive(f: &Integrand) -> Result<(Complex, f64, usize), String> {
+    let (a, b) = f.bounds();
+    let mut intervals = vec![(a, b, kronrod(f, a, b)?)];
+
+    loop {
+        let total = intervals.iter().fold((0.0, 0.0), |acc, &(_, _, (r, _))| c_add(acc, r));
+        let error: f64 = intervals.iter().map(|&(_, _, (_, e))| e).sum();
+
+        if error <= TOLERANCE.max(TOLERANCE * c_abs(total)) || intervals.len() >= MAX_INTERVALS {
+            return Ok((total, error, intervals.len()));
+        }
+        let worst = (0..intervals.len())
+            .fold(0, |worst, i| if (intervals[i].2).1 > (intervals[worst].2).1 { i } else { worst });
+        let (x, y, _) = intervals.swap_remove(worst);
+        let middle = (x + y) / 2.0;
+
+        intervals.push((x, middle, kronrod(f, x, middle)?));
+        intervals.push((middle, y, kronrod(f, middle, y)?));
+    }
+}
+
+// A bound is a number, inf, or -inf as reduced by the solver (-1 * inf).
+fn get_bound(solver: &Solver, expr: Expr) -> Result<f64, String> {
+    match solver.eval(expr)? {
+        Expr::Number(a) => Ok(a as f64),
+        Expr::Variable(ref s) if s.to_lowercase() == "inf" => Ok(f64::INFINITY),
+        Expr::Op(box Expr::Number(n), Opcode::Mul, box Expr::Variable(ref s)) if s.to_lowercase() == "inf" && n < 0.0 =>
+            Ok(f64::NEG_INFINITY),
+        e => Err(format!("expected a bound, found '{}'", e)),
+    }
+}
+
+// The integral followed by its error estimate, an error when the tolerance is still not reached
+// with MAX_INTERVALS subintervals.
+pub fn nintegrate(solver: &Solver, args: Vec<Expr>) -> Result<Expr, String> {
+    check_arity("nintegrate", &args, 3, 3)?;
+    let mut args = args.into_iter();
+    let name = get_name(args.next().unwrap())?;
+    let (var, expr) = match solver.get_function(&name) {
+        Some(function) => function,
+        None => return Err(format!("Error: Function '{}' is undefined.", name)),
+    };
+    let a = get_bound(solver, args.next().unwrap())?;
+    let b = get_bound(solver, args.next().unwrap())?;
+    let (a, b, sign) = if a > b { (b, a, -1.0) } else { (a, b, 1.0) };
+    let domain = match (a.is_infinite(), b.is_infinite()) {
+        _ if a == b => return Ok(Expr::List(vec![Box::new(Expr::Number(0.0)); 2])),
+        (false, false) => Domain::Finite(a, b),
+        (false, true) => Domain::Upper(a),
+        (true, false) => Domain::Lower(b),
+        (true, true) => Domain::Whole,
+    };
+    let integrand = Integrand { solver, expr, var, domain };
+    let (res, error, intervals) = adaptive(&integrand)?;
+
+    if !res.0.is_finite() || !res.1.is_finite() {
+        return Err(format!("nintegrate: the integral diverges"));
+    }
+    if intervals >= MAX_INTERVALS && error > TOLERANCE.max(TOLERANCE * c_abs(res)) {
+        return Err(format!("nintegrate: no convergence after {} subintervals, the error estimate is {}", intervals, error));
+    }
+    Ok(Expr::List(vec![
+        Box::new(from_complex((sign * res.0, sign * res.1))),
+        Box::new(Expr::Number(error as f32)),
+    ]))
+}
+
+#[cfg(test)]
+mod tests {
+    use ast::Expr;
+    use solver::Solver;
+
+    fn nintegrate(function: &str, bounds: &str) -> Result<(Expr, f32), String> {
+        let mut solver = Solver::new();
+
+        solver.run(&format!("f(x) = {}", function))?;
+        match solver.run(&format!("nintegrate(f, {}) = ?", bounds))? {
+            Expr::List(ref list) if list.len() == 2 => match *list[1] {
+                Expr::Number(error) => Ok(((*list[0]).clone(), error)),
+                _ => panic!("{}: not a report", function),
+            },
+            e => panic!("{}: unexpected {}", function, e),
+        }
+    }
+
+    fn value(function: &str, bounds: &str) -> f32 {
+        match nintegrate(function, bounds).unwrap().0 {
+            Expr::Number(a) => a,
+            e => panic!("{}: not a number {}", function, e),
+        }
+    }
+
+    #[test]
+    fn finite_and_improper_intervals() {
+        assert!((value("x^2", "0, 1") - 1.0 / 3.0).abs() < 1e-6);
+        assert!((value("x^2", "1, 0") + 1.0 / 3.0).abs() < 1e-6);
+        assert!((value("exp(0 - x)", "0, inf") - 1.0).abs() < 1e-5);
+        assert!((value("1 / (x^2 + 1)", "(-1) * inf, inf") - ::std::f32::consts::PI).abs() < 1e-4);
+        assert!((value("1 / sqrt(x)", "0, 1") - 2.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn complex_and_empty_intervals() {
+        match nintegrate("i * x", "0, 2").unwrap().0 {
+            Expr::Complex(a, b) => assert!(a.abs() < 1e-6 && (b - 2.0).abs() < 1e-5),
+            e => panic!("unexpected {}", e),
+        }
+        assert_eq!(nintegrate("x", "1, 1").unwrap(), (Expr::Number(0.0), 0.0));
+    }
+
+    #[test]
+    fn error_estimates_are_reported() {
+        let (_, error) = nintegrate("x^2", "0, 1").unwrap();
+        let (_, error_improper) = nintegrate("exp(0 - x)", "0, inf").unwrap();
+
+        assert!((0.0..1e-6).contains(&error));
+        assert!((0.0..1e-5).contains(&error_improper));
+
+        let mut solver = Solver::new();
+
+        solver.run("f(x) = cos(x)").unwrap();
+        solver.run("r = nintegrate(f, 0, 1)").unwrap();
+        assert!(match solver.run("r[0] = ?") { Ok(Expr::Number(a)) => (a - 1f32.sin()).abs() < 1e-5, _ => false });
+    }
+
+    #[test]
+    fn singular_endpoints_diverge() {
+        assert!(nintegrate("1 / x", "0, 1").unwrap_err().contains("diverges"));
+        assert!(nintegrate("1 / x^2", "(-1), 1").unwrap_err().contains("diverges"));
+    }
+
+    #[test]
+    fn other_errors_are_kept() {
+        let mut solver = Solver::new();
+
+        solver.run("f(x) = x * g(x)").unwrap();
+        assert_eq!(solver.run("nintegrate(f, 0, 1) = ?").unwrap_err(), "Error: Function 'g' is undefined.");
+    }
+}
@@ -0,0 +1,101 @@
+use ast::Expr;
+use solver::Solver;
+use builtins::{check_arity, get_matrix, get_number};
+use linalg::{Rows, from_rows, to_rows};
+
+// Folds all entries, or along axis 0 (each column) or 1 (each row) of a matrice.
+// A vector has axis 0 only, along which it folds to a single value as without an axis.
+fn reduce<F>(solver: &Solver, name: &str, args: Vec<Expr>, op: F) -> Result<Expr, String>
+    where F: Fn(Expr, Expr) -> Result<Expr, String> {
+    check_arity(name, &args, 1, 2)?;
+    let target = solver.eval(args[0].clone())?;
+    let fold = |values: Vec<Expr>| -> Result<Expr, String> {
+        let mut values = values.into_iter();
+        let first = values.next().unwrap();
+
+        values.fold(Ok(first), |acc, value| op(acc?, value))
+    };
+    if let Expr::Vector(vector) = target {
+        return match args.get(1) {
+            Some(axis) => match get_number(solver, axis.clone())? {
+                a if a == 0.0 => fold(vector),
+                a => Err(format!("{}: expected axis 0 for a vector, found {}", name, a)),
+            },
+            None => fold(vector),
+        };
+    }
+    let rows = to_rows(get_matrix(solver, target)?);
+    let columns = |rows: &Rows| (0..rows[0].len()).map(|j| rows.iter().map(|line| line[j].clone()).collect::<Vec<_>>()).collect::<Vec<_>>();
+
+    if args.len() == 1 {
+        return fold(rows.into_iter().flat_map(|line| line.into_iter()).collect());
+    }
+    match get_number(solver, args[1].clone())? {
+        a if a == 0.0 => Ok(from_rows(vec![columns(&rows).into_iter().map(&fold).collect::<Result<_, _>>()?])),
+        a if a == 1.0 => Ok(from_rows(rows.into_iter().map(|line| fold(line).map(|e| vec![e])).collect::<Result<_, _>>()?)),
+        a => Err(format!("{}: expected axis 0 or 1, found {}", name, a)),
+    }
+}
+
+fn compare(name: &str, a: Expr, b: Expr, keep_first: fn(f32, f32) -> bool) -> Result<Expr, String> {
+    match (a, b) {
+        (Expr::Number(x), Expr::Number(y)) => Ok(Expr::Number(if keep_first(x, y) { x } else { y })),
+        (Expr::Number(_), e) | (e, _) => Err(format!("{}: can't compare '{}'", name, e)),
+    }
+}
+
+pub fn sum(solver: &Solver, args: Vec<Expr>) -> Result<Expr, String> {
+    reduce(solver, "sum", args, |a, b| a + b)
+}
+
+pub fn prod(solver: &Solver, args: Vec<Expr>) -> Result<Expr, String> {
+    reduce(solver, "prod", args, |a, b| a * b)
+}
+
+pub fn min(solver: &Solver, args: Vec<Expr>) -> Result<Expr, String> {
+    reduce(solver, "min", args, |a, b| compare("min", a, b, |x, y| x <= y))
+}
+
+pub fn max(solver: &Solver, args: Vec<Expr>) -> Result<Expr, String> {
+    reduce(solver, "max", args, |a, b| compare("max", a, b, |x, y| x >= y))
+}
+
+#[cfg(test)]
+mod tests {
+    use solver::tests::run;
+
+    #[test]
+    fn sums_and_products() {
+        assert_eq!(run("sum([[1,2];[3,4]])").unwrap(), "10");
+        assert_eq!(run("sum([[1,2];[3,4]], 0)").unwrap(), "[ [4, 6] ]");
+        assert_eq!(run("sum([[1,2];[3,4]], 1)").unwrap(), "[ [3] ; [7] ]");
+        assert_eq!(run("prod([[1,2];[3,4]])").unwrap(), "24");
+        assert_eq!(run("prod([[1,2];[3,4]], 0)").unwrap(), "[ [3, 8] ]");
+        assert_eq!(run("prod([[1,2];[3,4]], 1)").unwrap(), "[ [2] ; [12] ]");
+    }
+
+    #[test]
+    fn minimums_and_maximums() {
+        assert_eq!(run("min([[4,2];[3,5]])").unwrap(), "2");
+        assert_eq!(run("min([[4,2];[3,5]], 0)").unwrap(), "[ [3, 2] ]");
+        assert_eq!(run("min([[4,2];[3,5]], 1)").unwrap(), "[ [2] ; [3] ]");
+        assert_eq!(run("max([[4,2];[3,5]])").unwrap(), "5");
+        assert_eq!(run("max([[4,2];[3,5]], 0)").unwrap(), "[ [4, 5] ]");
+        assert_eq!(run("max([[4,2];[3,5]], 1)").unwrap(), "[ [4] ; [5] ]");
+    }
+
+    #[test]
+    fn vectors_have_a_single_axis() {
+        assert_eq!(run("sum([1,2,3])").unwrap(), "6");
+        assert_eq!(run("sum([1,2,3], 0)").unwrap(), "6");
+        assert_eq!(run("max([1,3,2], 0)").unwrap(), "3");
+        assert_eq!(run("sum([1,2,3], 1)").unwrap_err(), "sum: expected axis 0 for a vector, found 1");
+    }
+
+    #[test]
+    fn invalid_arguments() {
+        assert_eq!(run("sum([[1,2];[3,4]], 2)").unwrap_err(), "sum: expected axis 0 or 1, found 2");
+        assert_eq!(run("max([[i,1]])").unwrap_err(), "max: can't compare 'i'");
+        assert!(run("min(1, 2, 3)").is_err());
+    }
+}
@@ -1,6 +1,11 @@
 use ast::{Expr, Opcode};
 use std::f32;
 use std::ops::Rem;
+use broadcast::{any_matrix, is_scalar, matrix_any, numeric_matrix_matrix};
+use linalg::Complex;
+use tensor::{element_wise, is_tensor};
+use vector;
+use sparse;
 
 fn rem_number_complex(a: f32, c: (f32, f32)) -> Result<Expr, String> {
     let n: i32 = a as i32;
@@ -41,16 +46,30 @@ fn rem_imaginary_complex(c: (f32, f32)) -> Result<Expr, String> {
     } 
 }
 
+// Integer remainder of real entries, as for two numbers, the other cases left to the scalar arms.
+fn rem_numeric(a: Complex, b: Complex) -> Option<Complex> {
+    match (a, b) {
+        ((x, 0.0), (y, 0.0)) if y as i32 != 0 => Some(((x as i32 % y as i32) as f64, 0.0)),
+        _ => None,
+    }
+}
+
 impl Rem for Expr {
     type Output = Result<Expr, String>;
 
     fn rem(self, other: Expr) -> Result<Expr, String> {
         match (self, other) {
+            (a, b) if is_tensor(&a) || is_tensor(&b) => element_wise(a, b, |x, y| x % y),
+            (a @ Expr::Vector(_), b) | (a, b @ Expr::Vector(_)) => vector::element_wise(a, b, |x, y| x % y),
             (Expr::Number(a), Expr::Number(b)) if b as i32 != 0 => Ok(Expr::Number((a as i32 % b as i32) as f32)),
             (Expr::Number(a), Expr::Complex(ca, cb)) => rem_number_complex(a, (ca, cb)),
             (Expr::Complex(c1a, c1b), Expr::Complex(c2a, c2b)) => rem_complex_complex((c1a, c1b), (c2a, c2b)),
             (Expr::Complex(ca, cb), Expr::Imaginary) => rem_complex_imaginary((ca, cb)),
             (Expr::Imaginary, Expr::Complex(ca, cb)) => rem_imaginary_complex((ca, cb)),
+            (Expr::Sparse(_), _) | (_, Expr::Sparse(_)) => Err(sparse::unsupported("the remainder")),
+            (Expr::Matrix(a), Expr::Matrix(b)) => numeric_matrix_matrix(a.into_rows()?, b.into_rows()?, rem_numeric, |x, y| x % y),
+            (Expr::Matrix(a), b) if is_scalar(&b) => matrix_any(a.into_rows()?, b, |x, y| x % y),
+            (a, Expr::Matrix(b)) if is_scalar(&a) => any_matrix(a, b.into_rows()?, |x, y| x % y),
             (a, b) => Ok(Expr::Op(Box::new(a), Opcode::Rem, Box::new(b))),
         }
     }
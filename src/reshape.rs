@@ -0,0 +1,115 @@
+use ast::Expr;
+use solver::Solver;
+use builtins::{check_arity, get_matrix, get_name, get_number};
+use linalg::{Rows, from_rows, to_rows};
+use vector::map_entries;
+
+fn get_matrices(solver: &Solver, args: Vec<Expr>) -> Result<Vec<Rows>, String> {
+    args.into_iter().map(|e| get_matrix(solver, e).map(to_rows)).collect()
+}
+
+// Shapes as [width, height], like the other dimension errors.
+fn dimension_error(expected: (usize, usize), found: (usize, usize)) -> String {
+    format!("invalid dimensions\n\texpected: [{}, {}]\n\tfound: [{}, {}]", expected.0, expected.1, found.0, found.1)
+}
+
+// Side by side, every matrice having the same number of rows.
+pub fn hstack(solver: &Solver, args: Vec<Expr>) -> Result<Expr, String> {
+    check_arity("hstack", &args, 1, usize::max_value())?;
+    let mut matrices = get_matrices(solver, args)?.into_iter();
+    let mut res = matrices.next().unwrap();
+
+    for rows in matrices {
+        if rows.len() != res.len() {
+            return Err(dimension_error((rows[0].len(), res.len()), (rows[0].len(), rows.len())));
+        }
+        for (line, other) in res.iter_mut().zip(rows.into_iter()) {
+            line.extend(other);
+        }
+    }
+    Ok(from_rows(res))
+}
+
+// On top of each other, every matrice having the same number of columns.
+pub fn vstack(solver: &Solver, args: Vec<Expr>) -> Result<Expr, String> {
+    check_arity("vstack", &args, 1, usize::max_value())?;
+    let mut matrices = get_matrices(solver, args)?.into_iter();
+    let mut res = matrices.next().unwrap();
+
+    for rows in matrices {
+        if rows[0].len() != res[0].len() {
+            return Err(dimension_error((res[0].len(), rows.len()), (rows[0].len(), rows.len())));
+        }
+        res.extend(rows);
+    }
+    Ok(from_rows(res))
+}
+
+// Same entries in row-major order with a new shape.
+pub fn reshape(solver: &Solver, args: Vec<Expr>) -> Result<Expr, String> {
+    check_arity("reshape", &args, 3, 3)?;
+    let rows = to_rows(get_matrix(solver, args[0].clone())?);
+    let (n, m) = (get_number(solver, args[1].clone())?, get_number(solver, args[2].clone())?);
+    let size = rows.len() * rows[0].len();
+
+    if n < 1.0 || m < 1.0 || n != n.round() || m != m.round() || (n * m) as usize != size {
+        return Err(format!("reshape: can't reshape a matrice of {} entries to [{}, {}]", size, n, m));
+    }
+    let values: Vec<Expr> = rows.into_iter().flat_map(|line| line.into_iter()).collect();
+
+    Ok(from_rows(values.chunks(m as usize).map(|line| line.to_vec()).collect()))
+}
+
+// Applies a user function, or a builtin of one argument, to every entry.
+pub fn map(solver: &Solver, args: Vec<Expr>) -> Result<Expr, String> {
+    check_arity("map", &args, 2, 2)?;
+    let name = get_name(args[0].clone())?;
+    let rows = match solver.eval(args[1].clone())? {
+        Expr::Vector(vector) => return map_entries(vector, |e| solver.eval(Expr::Function(name.clone(), Box::new(e)))),
+        e => to_rows(get_matrix(solver, e)?),
+    };
+    let mut res = Vec::with_capacity(rows.len());
+
+    for (y, line) in rows.into_iter().enumerate() {
+        let mut res_line = Vec::with_capacity(line.len());
+
+        for (x, value) in line.into_iter().enumerate() {
+            match solver.eval(Expr::Function(name.clone(), Box::new(value))) {
+                Ok(value) => res_line.push(value),
+                Err(s) => return Err(format!("{} at [{}, {}]", s, x, y)),
+            }
+        }
+        res.push(res_line);
+    }
+    Ok(from_rows(res))
+}
+
+#[cfg(test)]
+mod tests {
+    use solver::tests::run;
+
+    #[test]
+    fn stacking() {
+        assert_eq!(run("hstack([[1];[2]], [[3,4];[5,6]])").unwrap(), "[ [1, 3, 4] ; [2, 5, 6] ]");
+        assert_eq!(run("vstack([[1,2]], [[3,4];[5,6]])").unwrap(), "[ [1, 2] ; [3, 4] ; [5, 6] ]");
+    }
+
+    #[test]
+    fn mismatched_blocks() {
+        assert_eq!(run("hstack([[1];[2]], [[1,2,3]])").unwrap_err(), "invalid dimensions\n\texpected: [3, 2]\n\tfound: [3, 1]");
+        assert_eq!(run("vstack([[1,2]], [[1,2,3];[4,5,6]])").unwrap_err(), "invalid dimensions\n\texpected: [2, 2]\n\tfound: [3, 2]");
+    }
+
+    #[test]
+    fn reshaping() {
+        assert_eq!(run("reshape([[1,2,3];[4,5,6]], 3, 2)").unwrap(), "[ [1, 2] ; [3, 4] ; [5, 6] ]");
+        assert!(run("reshape([[1,2,3];[4,5,6]], 4, 2)").unwrap_err().contains("can't reshape"));
+    }
+
+    #[test]
+    fn mapping_keeps_vectors() {
+        assert_eq!(run("map(abs, [(-1), 2, (-3)])").unwrap(), "[1, 2, 3]");
+        assert_eq!(run("map(abs, [[(-1)];[2]])").unwrap(), "[ [1] ; [2] ]");
+        assert!(run("map(ln, [1, 0])").unwrap_err().contains("at [1]"));
+    }
+}
@@ -0,0 +1,223 @@
+use ast::Expr;
+use solver::Solver;
+use builtins::{check_arity, get_number, get_name};
+use std::f64;
+
+const MAX_ITERATIONS: usize = 100;
+const TOLERANCE: f64 = 1e-6;
+
+fn eval_at(solver: &Solver, expr: &Expr, var: &str, x: f64) -> Result<f64, String> {
+    match solver.solve_function(expr.clone(), var.to_string(), Expr::Number(x as f32))? {
+        Expr::Number(a) => Ok(a as f64),
+        e => Err(format!("'{}' is not a number at {} = {}", e, var, x)),
+    }
+}
+
+// A root found by brent or newton, with the iterations it took and f at the root.
+struct Convergence {
+    root: f64,
+    iterations: usize,
+    residual: f64,
+}
+
+// The root followed by its convergence report: the number of iterations and |f(x)|.
+fn report(found: Convergence) -> Expr {
+    Expr::List(vec![
+        Box::new(Expr::Number(found.root as f32)),
+        Box::new(Expr::Number(found.iterations as f32)),
+        Box::new(Expr::Number(found.residual.abs() as f32)),
+    ])
+}
+
+// Brent's method, as in Numerical Recipes' zbrent.
+fn brent(solver: &Solver, expr: &Expr, var: &str, mut a: f64, mut b: f64) -> Result<Convergence, String> {
+    let mut fa = eval_at(solver, expr, var, a)?;
+    let mut fb = eval_at(solver, expr, var, b)?;
+
+    if fa * fb > 0.0 {
+        return Err(format!("no sign change on [{}, {}]", a, b));
+    }
+    let (mut c, mut fc) = (b, fb);
+    let (mut d, mut e) = (b - a, b - a);
+
+    for iteration in 1..(MAX_ITERATIONS + 1) {
+        if (fb > 0.0 && fc > 0.0) || (fb < 0.0 && fc < 0.0) {
+            c = a;
+            fc = fa;
+            d = b - a;
+            e = d;
+        }
+        if fc.abs() < fb.abs() {
+            a = b;
+            b = c;
+            c = a;
+            fa = fb;
+            fb = fc;
+            fc = fa;
+        }
+        let tolerance = 2.0 * f64::EPSILON * b.abs() + 0.5 * TOLERANCE;
+        let middle = 0.5 * (c - b);
+
+        if middle.abs() <= tolerance || fb == 0.0 {
+            return Ok(Convergence { root: b, iterations: iteration, residual: fb });
+        }
+        if e.abs() >= tolerance && fa.abs() > fb.abs() {
+            let s = fb / fa;
+            let (mut p, mut q) = if a == c {
+                (2.0 * middle * s, 1.0 - s)
+            } else {
+                let (q, r) = (fa / fc, fb / fc);
+
+                (s * (2.0 * middle * q * (q - r) - (b - a) * (r - 1.0)), (q - 1.0) * (r - 1.0) * (s - 1.0))
+            };
+
+            if p > 0.0 {
+                q = -q;
+            }
+            p = p.abs();
+            if 2.0 * p < (3.0 * middle * q - (tolerance * q).abs()).min((e * q).abs()) {
+                e = d;
+                d = p / q;
+            } else {
+                d = middle;
+                e = d;
+            }
+        } else {
+            d = middle;
+            e = d;
+        }
+        a = b;
+        fa = fb;
+        b += if d.abs() > tolerance { d } else { tolerance.abs() * middle.signum() };
+        fb = eval_at(solver, expr, var, b)?;
+    }
+    Err(format!("brent: no convergence after {} iterations", MAX_ITERATIONS))
+}
+
+// Sign change on an interval around x growing geometrically, points failing to evaluate being skipped.
+fn bracket(solver: &Solver, expr: &Expr, var: &str, x: f64) -> Option<(f64, f64)> {
+    let fx = eval_at(solver, expr, var, x).ok()?;
+    let mut width = 1e-3 * x.abs().max(1.0);
+
+    for _ in 0..MAX_ITERATIONS {
+        for &y in [x - width, x + width].iter() {
+            match eval_at(solver, expr, var, y) {
+                Ok(fy) if fx * fy <= 0.0 => return Some((x.min(y), x.max(y))),
+                _ => (),
+            }
+        }
+        width *= 2.0;
+    }
+    None
+}
+
+// Newton's method with a central difference derivative, falling back to Brent on a flat guess.
+fn newton(solver: &Solver, expr: &Expr, var: &str, mut x: f64) -> Result<Convergence, String> {
+    for iteration in 1..(MAX_ITERATIONS + 1) {
+        let fx = eval_at(solver, expr, var, x)?;
+        let h = 1e-3 * x.abs().max(1.0);
+        let dfx = (eval_at(solver, expr, var, x + h)? - eval_at(solver, expr, var, x - h)?) / (2.0 * h);
+
+        if fx == 0.0 {
+            return Ok(Convergence { root: x, iterations: iteration, residual: fx });
+        }
+        if dfx == 0.0 {
+            return match bracket(solver, expr, var, x) {
+                Some((a, b)) => brent(solver, expr, var, a, b)
+                    .map(|found| Convergence { iterations: iteration + found.iterations, ..found }),
+                None => Err(format!("newton: zero derivative at {} = {} and no sign change around it", var, x)),
+            };
+        }
+        let step = fx / dfx;
+
+        x -= step;
+        if !x.is_finite() {
+            return Err(format!("newton: diverged"));
+        }
+        if step.abs() <= TOLERANCE * (1.0 + x.abs()) {
+            return Ok(Convergence { root: x, iterations: iteration, residual: eval_at(solver, expr, var, x)? });
+        }
+    }
+    Err(format!("newton: no convergence after {} iterations", MAX_ITERATIONS))
+}
+
+pub fn root(solver: &Solver, args: Vec<Expr>) -> Result<Expr, String> {
+    check_arity("root", &args, 3, 3)?;
+    let mut args = args.into_iter();
+    let name = get_name(args.next().unwrap())?;
+    let (var, expr) = match solver.get_function(&name) {
+        Some(function) => function,
+        None => return Err(format!("Error: Function '{}' is undefined.", name)),
+    };
+    let a = get_number(solver, args.next().unwrap())? as f64;
+    let b = get_number(solver, args.next().unwrap())? as f64;
+
+    Ok(report(brent(solver, &expr, &var, a, b)?))
+}
+
+pub fn solve(solver: &Solver, args: Vec<Expr>) -> Result<Expr, String> {
+    check_arity("solve", &args, 2, 3)?;
+    let mut args = args.into_iter();
+    let expr = args.next().unwrap();
+    let var = get_name(args.next().unwrap())?;
+    let guess = match args.next() {
+        Some(guess) => get_number(solver, guess)? as f64,
+        None => 0.0,
+    };
+
+    Ok(report(newton(solver, &expr, &var, guess)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use ast::Expr;
+    use solver::Solver;
+
+    fn report(solver: &mut Solver, line: &str) -> (f32, f32, f32) {
+        match solver.run(line) {
+            Ok(Expr::List(ref list)) if list.len() == 3 => match (&*list[0], &*list[1], &*list[2]) {
+                (&Expr::Number(x), &Expr::Number(n), &Expr::Number(r)) => (x, n, r),
+                _ => panic!("{}: not a report", line),
+            },
+            res => panic!("{}: unexpected {:?}", line, res.map(|e| format!("{}", e))),
+        }
+    }
+
+    #[test]
+    fn brent_on_a_bracket() {
+        let mut solver = Solver::new();
+
+        solver.run("f(x) = x^2 - 2").unwrap();
+        let (x, iterations, residual) = report(&mut solver, "root(f, 0, 2) = ?");
+
+        assert!((x - 2f32.sqrt()).abs() < 1e-5 && iterations >= 1.0 && residual < 1e-5);
+        assert!(solver.run("root(f, 2, 3) = ?").is_err());
+    }
+
+    #[test]
+    fn newton_from_a_guess() {
+        let mut solver = Solver::new();
+        let (x, iterations, residual) = report(&mut solver, "solve(x^2 - 2 = 0, x, 1) = ?");
+
+        assert!((x - 2f32.sqrt()).abs() < 1e-5 && iterations >= 1.0 && residual < 1e-5);
+    }
+
+    #[test]
+    fn newton_on_a_flat_guess() {
+        let mut solver = Solver::new();
+        let (x, _, residual) = report(&mut solver, "solve(x^2 - 2 = 0, x) = ?");
+
+        assert!((x.abs() - 2f32.sqrt()).abs() < 1e-5 && residual < 1e-5);
+        assert!(solver.run("solve(x^2 + 1 = 0, x) = ?").is_err());
+    }
+
+    #[test]
+    fn report_parts_can_be_indexed() {
+        let mut solver = Solver::new();
+
+        solver.run("r = solve(x - 3 = 0, x, 1)").unwrap();
+        assert!(match solver.run("r[0] = ?") { Ok(Expr::Number(x)) => (x - 3.0).abs() < 1e-5, _ => false });
+        assert!(match solver.run("r[1] = ?") { Ok(Expr::Number(n)) => n >= 1.0, _ => false });
+        assert!(match solver.run("r[2] = ?") { Ok(Expr::Number(r)) => r < 1e-5, _ => false });
+    }
+}
@@ -0,0 +1,297 @@
+use ast::{Expr, Opcode};
+use utils::{is_zero, cancel};
+
+type Coef = (f32, f32);
+
+#[derive(Clone)]
+pub struct Term {
+    pub coef: Coef,
+    pub factors: Vec<(String, Expr, f32)>,
+}
+
+pub type Sum = Vec<Term>;
+
+fn coef_is_zero(c: Coef) -> bool {
+    c.0 == 0.0 && c.1 == 0.0
+}
+
+fn coef_add(a: Coef, b: Coef) -> Coef {
+    (cancel(a.0 + b.0, a.0.abs().max(b.0.abs())), cancel(a.1 + b.1, a.1.abs().max(b.1.abs())))
+}
+
+fn coef_mul(a: Coef, b: Coef) -> Coef {
+    let (re, im) = ((a.0 * b.0, a.1 * b.1), (a.0 * b.1, a.1 * b.0));
+
+    (cancel(re.0 - re.1, re.0.abs().max(re.1.abs())), cancel(im.0 + im.1, im.0.abs().max(im.1.abs())))
+}
+
+fn coef_pow(c: Coef, n: f32) -> Option<Coef> {
+    let integer = is_zero(n - n.round());
+
+    if c.1 == 0.0 && (c.0 > 0.0 || integer) {
+        Some((c.0.powf(n), 0.0))
+    } else if integer {
+        let (module, angle) = ((c.0 * c.0 + c.1 * c.1).sqrt().powf(n), c.1.atan2(c.0) * n);
+
+        Some((cancel(module * angle.cos(), module), cancel(module * angle.sin(), module)))
+    } else {
+        None
+    }
+}
+
+pub fn coef_to_expr(c: Coef) -> Expr {
+    if c.1 == 0.0 {
+        Expr::Number(c.0 + 0.0)
+    } else {
+        Expr::Complex(c.0 + 0.0, c.1)
+    }
+}
+
+pub fn constant(c: Coef) -> Sum {
+    if coef_is_zero(c) {
+        vec![]
+    } else {
+        vec![Term { coef: c, factors: vec![] }]
+    }
+}
+
+pub fn atom(e: Expr) -> Sum {
+    atom_pow(e, 1.0)
+}
+
+fn atom_pow(e: Expr, n: f32) -> Sum {
+    vec![Term { coef: (1.0, 0.0), factors: vec![(format!("{}", e), e, n)] }]
+}
+
+pub fn as_constant(a: &[Term]) -> Option<Coef> {
+    match a.len() {
+        0 => Some((0.0, 0.0)),
+        1 if a[0].factors.is_empty() => Some(a[0].coef),
+        _ => None,
+    }
+}
+
+pub fn degree(term: &Term) -> f32 {
+    term.factors.iter().map(|&(_, _, n)| n).sum()
+}
+
+fn signature(term: &Term) -> String {
+    term.factors.iter().map(|&(ref key, _, n)| format!("{}^{};", key, n)).collect()
+}
+
+// Merges terms sharing the same factors and drops the null ones.
+fn collect(a: Sum) -> Sum {
+    let mut res: Sum = Vec::new();
+
+    for term in a {
+        let key = signature(&term);
+
+        match res.iter().position(|t| signature(t) == key) {
+            Some(index) => {
+                res[index].coef = coef_add(res[index].coef, term.coef);
+            },
+            None => res.push(term),
+        };
+    }
+    res.retain(|t| !coef_is_zero(t.coef));
+    res.sort_by(|a, b| {
+        degree(b).partial_cmp(&degree(a)).unwrap()
+            .then(signature(a).cmp(&signature(b)))
+    });
+    res
+}
+
+pub fn sum_add(a: Sum, b: Sum) -> Sum {
+    let mut res = a;
+
+    res.extend(b);
+    collect(res)
+}
+
+pub fn sum_neg(a: Sum) -> Sum {
+    a.into_iter().map(|t| Term { coef: (-t.coef.0, -t.coef.1), factors: t.factors }).collect()
+}
+
+fn term_mul(a: &Term, b: &Term) -> Term {
+    let mut factors = a.factors.clone();
+
+    for &(ref key, ref e, n) in b.factors.iter() {
+        match factors.iter().position(|&(ref k, _, _)| k == key) {
+            Some(index) => factors[index].2 += n,
+            None => factors.push((key.clone(), e.clone(), n)),
+        };
+    }
+    factors.retain(|&(_, _, n)| !is_zero(n));
+    factors.sort_by(|a, b| a.0.cmp(&b.0));
+    Term { coef: coef_mul(a.coef, b.coef), factors: factors }
+}
+
+fn has_factor(term: &Term, key: &str) -> bool {
+    term.factors.iter().any(|&(ref k, _, _)| k == key)
+}
+
+// Distributes when one side is a monomial, otherwise keeps sums as factors.
+pub fn sum_mul(a: Sum, b: Sum) -> Sum {
+    match (a.len(), b.len()) {
+        (1, 1) => return collect(vec![term_mul(&a[0], &b[0])]),
+        (1, _) | (_, 1) => {
+            let (term, sum) = if a.len() == 1 { (&a[0], &b) } else { (&b[0], &a) };
+            let factor = atom(to_expr(sum.clone()));
+
+            if has_factor(term, &factor[0].factors[0].0) {
+                return collect(vec![term_mul(term, &factor[0])]);
+            }
+        },
+        (0, _) | (_, 0) => return vec![],
+        _ => return vec![term_mul(&atom(to_expr(a))[0], &atom(to_expr(b))[0])],
+    };
+    sum_distribute(a, b)
+}
+
+fn sum_distribute(a: Sum, b: Sum) -> Sum {
+    let mut res: Sum = Vec::new();
+
+    for x in a.iter() {
+        for y in b.iter() {
+            res.push(term_mul(x, y));
+        }
+    }
+    collect(res)
+}
+
+pub fn sum_pow(a: Sum, n: f32) -> Result<Sum, String> {
+    match a.len() {
+        _ if is_zero(n) => Ok(constant((1.0, 0.0))),
+        0 if n > 0.0 => Ok(vec![]),
+        0 => Err(format!("Can't divide by 0")),
+        1 => match coef_pow(a[0].coef, n) {
+            Some(coef) => Ok(vec![Term {
+                coef: coef,
+                factors: a[0].factors.iter().map(|&(ref k, ref e, p)| (k.clone(), e.clone(), p * n)).collect(),
+            }]),
+            None => Ok(atom_pow(to_expr(a), n)),
+        },
+        _ if is_zero(n - 1.0) => Ok(a),
+        _ => Ok(atom_pow(to_expr(a), n)),
+    }
+}
+
+fn expand_pow(a: Sum, n: f32) -> Result<Sum, String> {
+    if a.len() > 1 && n > 1.0 && is_zero(n - n.round()) {
+        Ok((1..(n.round() as i32)).fold(a.clone(), |acc, _| sum_distribute(acc, a.clone())))
+    } else {
+        sum_pow(a, n)
+    }
+}
+
+fn reduce(expr: Expr, distribute: bool) -> Result<Sum, String> {
+    match expr {
+        Expr::Number(a) => Ok(constant((a, 0.0))),
+        Expr::Complex(a, b) => Ok(constant((a, b))),
+        Expr::Imaginary => Ok(constant((0.0, 1.0))),
+        Expr::Function(s, arg) => Ok(atom(Expr::Function(s, Box::new(to_expr(reduce(*arg, distribute)?))))),
+        Expr::Op(a, Opcode::Div, b) if distribute =>
+            Ok(sum_distribute(reduce(*a, true)?, sum_pow(reduce(*b, false)?, -1.0)?)),
+        Expr::Op(a, op, b) => {
+            let (a, b) = (reduce(*a, distribute)?, reduce(*b, distribute)?);
+
+            match op {
+                Opcode::Add => Ok(sum_add(a, b)),
+                Opcode::Sub => Ok(sum_add(a, sum_neg(b))),
+                Opcode::Mul if distribute => Ok(sum_distribute(a, b)),
+                Opcode::Mul => Ok(sum_mul(a, b)),
+                Opcode::Div => Ok(sum_mul(a, sum_pow(b, -1.0)?)),
+                Opcode::Pow => match as_constant(&b) {
+                    Some((n, im)) if is_zero(im) && distribute => expand_pow(a, n),
+                    Some((n, im)) if is_zero(im) => sum_pow(a, n),
+                    _ => Ok(atom(Expr::Op(Box::new(to_expr(a)), op, Box::new(to_expr(b))))),
+                },
+                op => Ok(atom(Expr::Op(Box::new(to_expr(a)), op, Box::new(to_expr(b))))),
+            }
+        },
+        e => Ok(atom(e)),
+    }
+}
+
+pub fn to_sum(expr: Expr) -> Result<Sum, String> {
+    reduce(expr, false)
+}
+
+fn product(factors: Vec<Expr>) -> Option<Expr> {
+    factors.into_iter().fold(None, |acc, e| match acc {
+        Some(acc) => Some(Expr::Op(Box::new(acc), Opcode::Mul, Box::new(e))),
+        None => Some(e),
+    })
+}
+
+fn factor_to_expr(e: &Expr, n: f32) -> Expr {
+    if is_zero(n - 1.0) {
+        e.clone()
+    } else {
+        Expr::Op(Box::new(e.clone()), Opcode::Pow, Box::new(Expr::Number(n)))
+    }
+}
+
+fn term_to_expr(term: &Term) -> Expr {
+    let numerator = product(term.factors.iter().filter(|&&(_, _, n)| n > 0.0)
+        .map(|&(_, ref e, n)| factor_to_expr(e, n)).collect());
+    let denominator = product(term.factors.iter().filter(|&&(_, _, n)| n < 0.0)
+        .map(|&(_, ref e, n)| factor_to_expr(e, -n)).collect());
+    let coef = coef_to_expr(term.coef);
+    let numerator = match (numerator, coef) {
+        (Some(e), Expr::Number(c)) if is_zero(c - 1.0) => e,
+        (Some(e), coef) => Expr::Op(Box::new(coef), Opcode::Mul, Box::new(e)),
+        (None, coef) => coef,
+    };
+
+    match denominator {
+        Some(d) => Expr::Op(Box::new(numerator), Opcode::Div, Box::new(d)),
+        None => numerator,
+    }
+}
+
+pub fn to_expr(a: Sum) -> Expr {
+    let mut res: Option<Expr> = None;
+
+    for term in a.iter() {
+        res = Some(match res {
+            None => term_to_expr(term),
+            Some(acc) if term.coef.1 == 0.0 && term.coef.0 < 0.0 =>
+                Expr::Op(Box::new(acc), Opcode::Sub, Box::new(term_to_expr(&sum_neg(vec![term.clone()])[0]))),
+            Some(acc) => Expr::Op(Box::new(acc), Opcode::Add, Box::new(term_to_expr(term))),
+        });
+    }
+    res.unwrap_or(Expr::Number(0.0))
+}
+
+pub fn simplify(expr: Expr) -> Result<Expr, String> {
+    Ok(to_expr(to_sum(expr)?))
+}
+
+pub fn expand(expr: Expr) -> Result<Expr, String> {
+    Ok(to_expr(reduce(expr, true)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use solver::Solver;
+    use solver::tests::{run, run_in};
+
+    #[test]
+    fn like_terms_are_collected() {
+        assert_eq!(run("(x + 1) + (x + 2)").unwrap(), "2 * x + 3");
+        assert_eq!(run("x * x - x^2").unwrap(), "0");
+        assert_eq!(run("(x + 1) / (x + 1)").unwrap(), "1");
+        assert_eq!(run("i * i * x").unwrap(), "-1 * x");
+    }
+
+    #[test]
+    fn small_coefficients_are_kept() {
+        let mut solver = Solver::new();
+
+        assert_eq!(run("0.0000001 * x").unwrap(), "0.0000001 * x");
+        run_in(&mut solver, "y = 0.0000001").unwrap();
+        assert_eq!(run_in(&mut solver, "x + y = ?").unwrap(), "x + 0.0000001");
+        assert_eq!(run("0.1 * x + 0.2 * x - 0.3 * x").unwrap(), "0");
+    }
+}
@@ -1,6 +1,12 @@
 use std::collections::HashMap;
 use std::f32;
 use ast::*;
+use equation::Equation;
+use builtins::{call_builtin, is_symbolic};
+use polynome::Polynome;
+use indexing::{index, assign_index};
+use tensor::Tensor;
+use vector::map_entries;
 
 pub struct Solver {
     vars: HashMap<String, Expr>,
@@ -48,7 +54,7 @@ impl Solver {
             res.push(res_line);
         }
         if errors.is_empty() {
-            Ok(Expr::Matrix(res))
+            Ok(Expr::matrix(res))
         } else {
             Err(errors)
         }
@@ -87,7 +93,7 @@ impl Solver {
             res.push(res_line);
         }
         if errors.is_empty() {
-            Ok(Expr::Matrix(res))
+            Ok(Expr::matrix(res))
         } else {
             Err(errors)
         }
@@ -113,19 +119,77 @@ impl Solver {
             Some((arg_name, func_expr)) => {
                 self.solve_function(func_expr, arg_name, expr)
             },
-            None => Err(format!("Error: Function '{}' is undefined.", s))
+            None => call_builtin(self, &s.to_lowercase(), expr),
         }
     }
 
-    fn solve_function(&self, expr: Expr, arg_name: String, arg_value: Expr) -> Result<Expr, String> {
+    fn handle_list(&self, list: Vec<Box<Expr>>) -> Result<Expr, String> {
+        let mut res = Vec::<Box<Expr>>::new();
+
+        for value in list {
+            res.push(Box::new(self.solve(*value)?));
+        }
+        Ok(Expr::List(res))
+    }
+
+    fn solve_slices(&self, slices: Vec<Slice>) -> Result<Vec<Slice>, String> {
+        let mut res = Vec::<Slice>::new();
+
+        for slice in slices {
+            res.push(match slice {
+                Slice::Single(e) => Slice::Single(Box::new(self.solve(*e)?)),
+                Slice::Range(a, b) => Slice::Range(
+                    match a { Some(a) => Some(Box::new(self.solve(*a)?)), None => None },
+                    match b { Some(b) => Some(Box::new(self.solve(*b)?)), None => None },
+                ),
+            });
+        }
+        Ok(res)
+    }
+
+    pub fn substitute(&self, expr: Expr, arg_name: &str, arg_value: &Expr) -> Expr {
+        match expr {
+            Expr::Variable(ref s) if s.to_lowercase() == arg_name => arg_value.clone(),
+            Expr::Function(s, box expr) => Expr::Function(s, Box::new(self.substitute(expr, arg_name, arg_value))),
+            Expr::List(list) =>
+                Expr::List(list.into_iter().map(|e| Box::new(self.substitute(*e, arg_name, arg_value))).collect()),
+            Expr::Matrix(t) =>
+                Expr::Matrix(Tensor { shape: t.shape, data: t.data.into_iter().map(|e| self.substitute(e, arg_name, arg_value)).collect() }),
+            Expr::Vector(vector) =>
+                Expr::Vector(vector.into_iter().map(|e| self.substitute(e, arg_name, arg_value)).collect()),
+            Expr::Op(a, op, b) =>
+                Expr::Op(Box::new(self.substitute(*a, arg_name, arg_value)), op,
+                         Box::new(self.substitute(*b, arg_name, arg_value))),
+            Expr::Index(e, slices) => {
+                let bound = |b: Option<Box<Expr>>| b.map(|b| Box::new(self.substitute(*b, arg_name, arg_value)));
+
+                Expr::Index(Box::new(self.substitute(*e, arg_name, arg_value)), slices.into_iter().map(|slice| match slice {
+                    Slice::Single(e) => Slice::Single(Box::new(self.substitute(*e, arg_name, arg_value))),
+                    Slice::Range(a, b) => Slice::Range(bound(a), bound(b)),
+                }).collect())
+            },
+            any => any,
+        }
+    }
+
+    pub fn solve_function(&self, expr: Expr, arg_name: String, arg_value: Expr) -> Result<Expr, String> {
         match expr {
             Expr::Number(a) => Ok(Expr::Number(a)),
             Expr::Imaginary => Ok(Expr::Imaginary),
             Expr::Complex(a, b) => self.handle_complex(a, b),
-            Expr::Matrix(matrice) => self.reduce_matrice_in_function(matrice, arg_name, arg_value),
+            Expr::Matrix(t) if t.shape.len() == 2 => self.reduce_matrice_in_function(t.into_rows()?, arg_name, arg_value),
+            Expr::Matrix(t) => Ok(Expr::Matrix(t.map(|e| self.solve_function(e, arg_name.clone(), arg_value.clone()))?)),
+            Expr::Sparse(s) => Ok(Expr::Sparse(s)),
+            Expr::Text(s) => Ok(Expr::Text(s)),
+            Expr::Vector(vector) => map_entries(vector, |e| self.solve_function(e, arg_name.clone(), arg_value.clone())),
             Expr::Variable(ref s) if s.to_lowercase() == arg_name => Ok(arg_value),
             Expr::Variable(s) => self.handle_variable(s),
+            Expr::Function(s, expr) if !self.funcs.contains_key(&s.to_lowercase()) =>
+                self.handle_function(s, self.substitute(*expr, &arg_name, &arg_value)),
             Expr::Function(s, expr) => self.handle_function(s, self.solve_function(*expr, arg_name, arg_value)?),
+            Expr::List(list) => self.handle_list(list.into_iter()
+                .map(|e| Box::new(self.substitute(*e, &arg_name, &arg_value))).collect()),
+            Expr::Index(e, slices) => self.solve(self.substitute(Expr::Index(e, slices), &arg_name, &arg_value)),
             Expr::Op(a, op, b) => match op {
                 Opcode::Add => self.solve_function(*a, arg_name.clone(), arg_value.clone())? + self.solve_function(*b, arg_name, arg_value)?,
                 Opcode::Mul => self.solve_function(*a, arg_name.clone(), arg_value.clone())? * self.solve_function(*b, arg_name, arg_value)?,
@@ -144,9 +208,15 @@ impl Solver {
             Expr::Number(a) => Ok(Expr::Number(a)),
             Expr::Imaginary => Ok(Expr::Imaginary),
             Expr::Complex(a, b) => self.handle_complex(a, b),
-            Expr::Matrix(matrice) => self.reduce_matrice(matrice),
+            Expr::Matrix(t) if t.shape.len() == 2 => self.reduce_matrice(t.into_rows()?),
+            Expr::Matrix(t) => Ok(Expr::Matrix(t.map(|e| self.solve(e))?)),
+            Expr::Sparse(s) => Ok(Expr::Sparse(s)),
+            Expr::Text(s) => Ok(Expr::Text(s)),
+            Expr::Vector(vector) => map_entries(vector, |e| self.solve(e)),
             Expr::Variable(s) => self.handle_variable(s),
             Expr::Function(s, expr) => self.handle_function(s, *expr),
+            Expr::List(list) => self.handle_list(list),
+            Expr::Index(e, slices) => index(self.solve(*e)?, self.solve_slices(slices)?),
             Expr::Op(a, op, b) => match op {
                 Opcode::Add => self.solve(*a)? + self.solve(*b)?,
                 Opcode::Mul => self.solve(*a)? * self.solve(*b)?,
@@ -184,7 +254,7 @@ impl Solver {
             res.push(res_line);
         }
         if errors.is_empty() {
-            Ok(Expr::Matrix(res))
+            Ok(Expr::matrix(res))
         } else {
             Err(errors)
         }
@@ -199,8 +269,12 @@ impl Solver {
                             op,
                             Box::new(self.clean_function(fun_str, arg_str, *b)?))),
             Expr::Function(ref s, _) if s.to_lowercase() == fun_str => Err(format!("recursive function: {}", s)),
+            Expr::Function(s, expr) if is_symbolic(&s.to_lowercase()) && !self.funcs.contains_key(&s.to_lowercase()) =>
+                self.solve(Expr::Function(s, expr)),
             Expr::Function(s, expr) => Ok(Expr::Function(s.to_lowercase(), expr)),
-            Expr::Matrix(a) => self.clean_matrix(fun_str, arg_str, a),
+            Expr::Matrix(a) if a.shape.len() == 2 => self.clean_matrix(fun_str, arg_str, a.into_rows()?),
+            Expr::Matrix(a) => Ok(Expr::Matrix(a.map(|e| self.clean_function(fun_str.clone(), arg_str.clone(), e))?)),
+            Expr::Vector(vector) => map_entries(vector, |e| self.clean_function(fun_str.clone(), arg_str.clone(), e)),
             any => Ok(any),
         }
     }
@@ -209,7 +283,11 @@ impl Solver {
         let fun_str = fun_str.to_lowercase();
         let arg_str = arg_str.to_lowercase();
         let expr = self.clean_function(fun_str.clone(), arg_str.clone(), expr)?;
-        
+        let expr = match Polynome::from_expr(&expr) {
+            Some(polynome) => polynome.to_expr(),
+            None => expr,
+        };
+
         self.funcs.insert(fun_str.clone(), (arg_str.clone(), expr));
         Ok(Expr::Function(fun_str, Box::new(Expr::Variable(arg_str))))
     }
@@ -222,9 +300,22 @@ impl Solver {
         Ok(res)
     }
 
+    fn assign_index(&mut self, var_str: String, slices: Vec<Slice>, expr: Expr) -> Result<Expr, String> {
+        let var_str = var_str.to_lowercase();
+        let target = match self.vars.get(&var_str).cloned() {
+            Some(target) => target,
+            None => return Err(format!("Error: variable '{}' is undefined.", var_str)),
+        };
+        let res = assign_index(target, self.solve_slices(slices)?, self.solve(expr)?)?;
+
+        self.vars.insert(var_str, res.clone());
+        Ok(res)
+    }
+
     pub fn assign(&mut self, left: Expr, expr: Expr) -> Result<Expr, String> {
         match left {
             Expr::Variable(var_str) => self.assign_variable(var_str, expr),
+            Expr::Index(box Expr::Variable(var_str), slices) => self.assign_index(var_str, slices, expr),
             Expr::Function(fun_str, box Expr::Variable(arg_str)) =>
                 self.assign_function(fun_str, arg_str, expr),
             Expr::Function(fun_str, box arg) => 
@@ -237,11 +328,26 @@ impl Solver {
         self.solve(expr)
     }
 
+    pub fn solve_equation(&self, left: Expr, right: Expr) -> Result<Equation, String> {
+        Equation::new(self, left, right)
+    }
+
+    pub fn get_variable(&self, name: &str) -> Option<Expr> {
+        self.vars.get(&name.to_lowercase()).cloned()
+    }
+
+    pub fn get_function(&self, name: &str) -> Option<(String, Expr)> {
+        self.funcs.get(&name.to_lowercase()).cloned()
+    }
+
     pub fn show_function(&self, expr: Expr) {
         match expr {
             Expr::Function(ref name, _) => {
                 match self.funcs.get(&name.to_lowercase()).cloned() {
-                    Some((arg, expr)) => println!("{}({}) = {}", name, arg, expr),
+                    Some((arg, expr)) => match Polynome::from_expr(&expr) {
+                        Some(polynome) => println!("{}({}) = {}", name, arg, polynome),
+                        None => println!("{}({}) = {}", name, arg, expr),
+                    },
                     None => println!("Error: function '{}' is undefined.", name),
                 }
             },
@@ -250,3 +356,31 @@ impl Solver {
     }
 }
 
+
+#[cfg(test)]
+impl Solver {
+    // Runs an assignation or an evaluation line as the REPL does.
+    pub fn run(&mut self, line: &str) -> Result<Expr, String> {
+        match ::parser::EquExprParser::new().parse(line) {
+            Ok(Input::Assignation(left, right)) => self.assign(*left, *right),
+            Ok(Input::Eval(expr)) => self.eval(*expr),
+            Ok(Input::Equation(_, _)) => Err(format!("equations are solved by solve_equation")),
+            Err(err) => Err(format!("{}", err)),
+        }
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::Solver;
+
+    // Evaluates "line = ?" in a fresh solver and formats the result.
+    pub fn run(line: &str) -> Result<String, String> {
+        Solver::new().run(&format!("{} = ?", line)).map(|e| format!("{}", e))
+    }
+
+    // Runs a full line in an existing solver and formats the result.
+    pub fn run_in(solver: &mut Solver, line: &str) -> Result<String, String> {
+        solver.run(line).map(|e| format!("{}", e))
+    }
+}
@@ -0,0 +1,464 @@
+use ast::Expr;
+use solver::Solver;
+use builtins::{check_arity, get_matrix};
+use linalg::{Complex, Numeric, c_abs, c_add, c_div, c_mul, c_sub, column, from_complex, to_complex};
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::f32;
+
+type Matrice = Vec<Vec<Box<Expr>>>;
+type Line = BTreeMap<usize, Complex>;
+
+// Compressed rows: the entries of row i are at offsets[i]..offsets[i + 1], in column order.
+#[derive(Clone)]
+pub struct Sparse {
+    pub rows: usize,
+    pub cols: usize,
+    offsets: Vec<usize>,
+    columns: Vec<usize>,
+    values: Vec<Complex>,
+}
+
+pub fn unsupported(operation: &str) -> String {
+    format!("{} is not supported on sparse matrices, convert them with full() first", operation)
+}
+
+pub fn numeric(matrice: &Matrice) -> Option<Numeric> {
+    matrice.iter().map(|line| line.iter().map(|e| to_complex(e)).collect()).collect()
+}
+
+impl Sparse {
+    // Zeros are dropped.
+    fn from_lines(cols: usize, lines: Vec<Line>) -> Sparse {
+        let mut res = Sparse { rows: lines.len(), cols, offsets: vec![0], columns: Vec::new(), values: Vec::new() };
+
+        for line in lines {
+            for (j, value) in line {
+                if value != (0.0, 0.0) {
+                    res.columns.push(j);
+                    res.values.push(value);
+                }
+            }
+            res.offsets.push(res.columns.len());
+        }
+        res
+    }
+
+    // Duplicate entries are summed.
+    pub fn identity(n: usize) -> Sparse {
+        Sparse::from_triplets(n, n, (0..n).map(|i| (i, i, (1.0, 0.0))).collect())
+    }
+
+    pub fn from_triplets(rows: usize, cols: usize, triplets: Vec<(usize, usize, Complex)>) -> Sparse {
+        let mut lines = vec![Line::new(); rows];
+
+        for (i, j, value) in triplets {
+            let entry = lines[i].entry(j).or_insert((0.0, 0.0));
+
+            *entry = c_add(*entry, value);
+        }
+        Sparse::from_lines(cols, lines)
+    }
+
+    // None as soon as an entry is symbolic.
+    pub fn from_matrice(matrice: &Matrice) -> Option<Sparse> {
+        let lines: Option<Vec<Line>> = matrice.iter().map(|line| {
+            line.iter().enumerate().map(|(j, e)| to_complex(e).map(|value| (j, value))).collect()
+        }).collect();
+
+        Some(Sparse::from_lines(matrice[0].len(), lines?))
+    }
+
+    pub fn entries(&self) -> usize {
+        self.values.len()
+    }
+
+    // Entries of row i as (column, value).
+    pub fn line<'a>(&'a self, i: usize) -> impl Iterator<Item = (usize, Complex)> + 'a {
+        let (start, end) = (self.offsets[i], self.offsets[i + 1]);
+
+        self.columns[start..end].iter().cloned().zip(self.values[start..end].iter().cloned())
+    }
+
+    // Zero when the entry isn't stored.
+    pub fn get(&self, i: usize, j: usize) -> Complex {
+        let start = self.offsets[i];
+
+        match self.columns[start..self.offsets[i + 1]].binary_search(&j) {
+            Ok(k) => self.values[start + k],
+            Err(_) => (0.0, 0.0),
+        }
+    }
+
+    pub fn triplets<'a>(&'a self) -> impl Iterator<Item = (usize, usize, Complex)> + 'a {
+        (0..self.rows).flat_map(move |i| self.line(i).map(move |(j, value)| (i, j, value)))
+    }
+
+    fn to_lines(&self) -> Vec<Line> {
+        (0..self.rows).map(|i| self.line(i).collect()).collect()
+    }
+
+    pub fn to_numeric(&self) -> Numeric {
+        let mut res = vec![vec![(0.0, 0.0); self.cols]; self.rows];
+
+        for (i, j, value) in self.triplets() {
+            res[i][j] = value;
+        }
+        res
+    }
+
+    pub fn to_matrice(&self) -> Matrice {
+        self.to_numeric().into_iter().map(|line| line.into_iter().map(|a| Box::new(from_complex(a))).collect()).collect()
+    }
+
+    pub fn map<F>(&self, op: F) -> Sparse where F: Fn(Complex) -> Complex {
+        Sparse::from_lines(self.cols, (0..self.rows).map(|i| self.line(i).map(|(j, value)| (j, op(value))).collect()).collect())
+    }
+
+    // Entries times a numeric scalar, None for any other operand.
+    pub fn scale(&self, factor: &Expr) -> Option<Sparse> {
+        to_complex(factor).map(|factor| self.map(|value| c_mul(value, factor)))
+    }
+
+    pub fn transpose(&self) -> Sparse {
+        let mut lines = vec![Line::new(); self.cols];
+
+        for (i, j, value) in self.triplets() {
+            lines[j].insert(i, value);
+        }
+        Sparse::from_lines(self.rows, lines)
+    }
+
+    pub fn check_shape(&self, (rows, cols): (usize, usize)) -> Result<(), String> {
+        if self.rows != rows || self.cols != cols {
+            return Err(format!("invalid dimensions\n\texpected: [{}, {}]\n\tfound: [{}, {}]", self.cols, self.rows, cols, rows));
+        }
+        Ok(())
+    }
+
+    // Same shape and entries equal up to f32::EPSILON, as for numbers.
+    pub fn approx_eq(&self, other: &Sparse) -> bool {
+        let close = |a: Complex, b: Complex| c_abs(c_sub(a, b)) <= f32::EPSILON as f64;
+
+        self.rows == other.rows && self.cols == other.cols
+            && self.triplets().all(|(i, j, a)| close(a, other.get(i, j)))
+            && other.triplets().all(|(i, j, b)| close(self.get(i, j), b))
+    }
+
+    // self + sign * other.
+    pub fn combine(&self, other: &Sparse, sign: f64) -> Result<Sparse, String> {
+        self.check_shape((other.rows, other.cols))?;
+        let mut lines = self.to_lines();
+
+        for (i, j, value) in other.triplets() {
+            let entry = lines[i].entry(j).or_insert((0.0, 0.0));
+
+            *entry = c_add(*entry, (sign * value.0, sign * value.1));
+        }
+        Ok(Sparse::from_lines(self.cols, lines))
+    }
+
+    // matrice + sign * self, only the stored entries being touched.
+    pub fn add_to(&self, mut matrice: Matrice, sign: f64) -> Result<Expr, String> {
+        self.check_shape((matrice.len(), matrice[0].len()))?;
+        for (i, j, value) in self.triplets() {
+            *matrice[i][j] = (*matrice[i][j].clone() + from_complex((sign * value.0, sign * value.1)))?;
+        }
+        Ok(Expr::matrix(matrice))
+    }
+
+    // Element-wise product, the zeros of self staying zeros.
+    pub fn mul_entries<F>(&self, other: F) -> Sparse where F: Fn(usize, usize) -> Complex {
+        Sparse::from_triplets(self.rows, self.cols, self.triplets().map(|(i, j, value)| (i, j, c_mul(value, other(i, j)))).collect())
+    }
+
+    // Square and multiply, None for a non-square matrice.
+    pub fn power(&self, mut exponent: u64) -> Option<Sparse> {
+        let (mut base, mut res) = (self.clone(), Sparse::identity(self.rows));
+
+        if self.rows != self.cols {
+            return None;
+        }
+        while exponent > 0 {
+            if exponent & 1 == 1 {
+                res = res.product(&base)?;
+            }
+            exponent >>= 1;
+            if exponent > 0 {
+                base = base.product(&base)?;
+            }
+        }
+        Some(res)
+    }
+
+    // Row by row accumulation, None when the inner dimensions differ.
+    pub fn product(&self, other: &Sparse) -> Option<Sparse> {
+        if self.cols != other.rows {
+            return None;
+        }
+        let lines = (0..self.rows).map(|i| {
+            let mut line = Line::new();
+
+            for (k, a) in self.line(i) {
+                for (j, b) in other.line(k) {
+                    let entry = line.entry(j).or_insert((0.0, 0.0));
+
+                    *entry = c_add(*entry, c_mul(a, b));
+                }
+            }
+            line
+        }).collect();
+
+        Some(Sparse::from_lines(other.cols, lines))
+    }
+
+    // self * b with b dense.
+    pub fn product_dense(&self, b: &Numeric) -> Option<Numeric> {
+        if self.cols != b.len() {
+            return None;
+        }
+        Some((0..self.rows).map(|i| {
+            let mut line = vec![(0.0, 0.0); b[0].len()];
+
+            for (k, a) in self.line(i) {
+                for (res, &value) in line.iter_mut().zip(b[k].iter()) {
+                    *res = c_add(*res, c_mul(a, value));
+                }
+            }
+            line
+        }).collect())
+    }
+
+    // a * self with a dense.
+    pub fn dense_product(&self, a: &Numeric) -> Option<Numeric> {
+        if a[0].len() != self.rows {
+            return None;
+        }
+        Some(a.iter().map(|a_line| {
+            let mut line = vec![(0.0, 0.0); self.cols];
+
+            for (k, &x) in a_line.iter().enumerate().filter(|&(_, &x)| x != (0.0, 0.0)) {
+                for (j, value) in self.line(k) {
+                    line[j] = c_add(line[j], c_mul(x, value));
+                }
+            }
+            line
+        }).collect())
+    }
+
+    // Gaussian elimination with partial pivoting, applying the same row operations to rhs.
+    // Upper triangular rows and the determinant, None for a singular matrice.
+    fn eliminate(&self, rhs: &mut Numeric) -> Option<(Vec<Line>, Complex)> {
+        let n = self.rows;
+        let epsilon = self.values.iter().map(|&a| c_abs(a)).fold(0.0, f64::max) * n as f64 * 8.0 * f32::EPSILON as f64;
+        let mut lines = self.to_lines();
+        let mut det = (1.0, 0.0);
+
+        for k in 0..n {
+            let pivot = (k..n).filter_map(|i| lines[i].get(&k).map(|&a| (i, c_abs(a))))
+                .fold(None, |best: Option<(usize, f64)>, (i, a)| match best {
+                    Some((_, b)) if b >= a => best,
+                    _ => Some((i, a)),
+                });
+            let p = match pivot {
+                Some((p, a)) if a > epsilon => p,
+                _ => return None,
+            };
+
+            if p != k {
+                lines.swap(p, k);
+                rhs.swap(p, k);
+                det = (-det.0, -det.1);
+            }
+            let head = lines[k][&k];
+            let pivot_line: Vec<(usize, Complex)> = lines[k].range((k + 1)..).map(|(&j, &a)| (j, a)).collect();
+            let pivot_rhs = rhs[k].clone();
+
+            det = c_mul(det, head);
+            for i in (k + 1)..n {
+                let factor = match lines[i].remove(&k) {
+                    Some(a) => c_div(a, head),
+                    None => continue,
+                };
+
+                for &(j, a) in pivot_line.iter() {
+                    let entry = lines[i].entry(j).or_insert((0.0, 0.0));
+
+                    *entry = c_sub(*entry, c_mul(factor, a));
+                }
+                for (res, &a) in rhs[i].iter_mut().zip(pivot_rhs.iter()) {
+                    *res = c_sub(*res, c_mul(factor, a));
+                }
+            }
+        }
+        Some((lines, det))
+    }
+
+    pub fn determinant(&self) -> Complex {
+        match self.eliminate(&mut vec![Vec::new(); self.rows]) {
+            Some((_, det)) => det,
+            None => (0.0, 0.0),
+        }
+    }
+
+    // Solution of self * x = rhs for every column of rhs, None for a singular matrice.
+    pub fn solve(&self, mut rhs: Numeric) -> Option<Numeric> {
+        let (lines, _) = self.eliminate(&mut rhs)?;
+
+        for k in (0..self.rows).rev() {
+            let head = lines[k][&k];
+
+            for c in 0..rhs[k].len() {
+                let sum = lines[k].range((k + 1)..).fold(rhs[k][c], |acc, (&j, &a)| c_sub(acc, c_mul(a, rhs[j][c])));
+
+                rhs[k][c] = c_div(sum, head);
+            }
+        }
+        Some(rhs)
+    }
+}
+
+pub fn check_square(name: &str, a: &Sparse) -> Result<usize, String> {
+    if a.rows != a.cols {
+        return Err(format!("{}: non-square matrice [{}, {}]", name, a.rows, a.cols));
+    }
+    Ok(a.rows)
+}
+
+pub fn linsolve(solver: &Solver, a: &Sparse, b: Expr) -> Result<Expr, String> {
+    let n = check_square("linsolve", a)?;
+    let b = match numeric(&get_matrix(solver, b)?) {
+        Some(b) => b,
+        None => return Err(format!("linsolve: expected numeric entries")),
+    };
+    let rhs = match (b.len(), b[0].len()) {
+        (y, 1) if y == n => b,
+        (1, x) if x == n => b[0].iter().map(|&a| vec![a]).collect(),
+        (y, x) => return Err(format!("linsolve: expected a vector of size {}, found [{}, {}]", n, y, x)),
+    };
+
+    match a.solve(rhs) {
+        Some(x) => Ok(column(x.into_iter().map(|line| line[0]).collect())),
+        None => Err(format!("linsolve: singular matrice, the system has no unique solution")),
+    }
+}
+
+pub fn sparse(solver: &Solver, args: Vec<Expr>) -> Result<Expr, String> {
+    check_arity("sparse", &args, 1, 1)?;
+    match solver.eval(args[0].clone())? {
+        e @ Expr::Sparse(_) => Ok(e),
+        Expr::Matrix(t) => match Sparse::from_matrice(&t.into_rows().map_err(|s| format!("sparse: {}", s))?) {
+            Some(sparse) => Ok(Expr::Sparse(sparse)),
+            None => Err(format!("sparse: expected numeric entries")),
+        },
+        e => Err(format!("sparse: expected a matrice, found '{}'", e)),
+    }
+}
+
+pub fn full(solver: &Solver, args: Vec<Expr>) -> Result<Expr, String> {
+    check_arity("full", &args, 1, 1)?;
+    match solver.eval(args[0].clone())? {
+        Expr::Sparse(s) => Ok(Expr::matrix(s.to_matrice())),
+        e => Ok(Expr::matrix(get_matrix(solver, e)?)),
+    }
+}
+
+pub fn load(solver: &Solver, args: Vec<Expr>) -> Result<Expr, String> {
+    check_arity("load", &args, 1, 1)?;
+    match solver.eval(args[0].clone())? {
+        Expr::Text(path) => read_triplets(&path).map(Expr::Sparse),
+        e => Err(format!("load: expected a quoted path, found '{}'", e)),
+    }
+}
+
+// One "row column value [imaginary]" entry per line, indices starting at 0.
+// A leading "rows columns" line gives the shape, otherwise it is taken from the largest indices.
+pub fn read_triplets(path: &str) -> Result<Sparse, String> {
+    let file = File::open(path).map_err(|e| format!("load: can't open '{}': {}", path, e))?;
+    let mut shape = None;
+    let mut triplets = Vec::new();
+
+    for (number, line) in BufReader::new(file).lines().enumerate() {
+        let line = line.map_err(|e| format!("load: can't read '{}': {}", path, e))?;
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        let invalid = || format!("load: invalid entry at line {}: '{}'", number + 1, line.trim());
+        let index = |s: &str| s.parse::<usize>().map_err(|_| invalid());
+        let value = |s: &str| s.parse::<f64>().map_err(|_| invalid());
+
+        if fields.is_empty() || fields[0].starts_with('#') || fields[0].starts_with('%') {
+            continue;
+        }
+        match fields.len() {
+            2 if shape.is_none() && triplets.is_empty() => shape = Some((index(fields[0])?, index(fields[1])?)),
+            3 => triplets.push((index(fields[0])?, index(fields[1])?, (value(fields[2])?, 0.0))),
+            4 => triplets.push((index(fields[0])?, index(fields[1])?, (value(fields[2])?, value(fields[3])?))),
+            _ => return Err(invalid()),
+        }
+    }
+    let (rows, cols) = shape.unwrap_or_else(|| {
+        triplets.iter().fold((0, 0), |(n, m), &(i, j, _)| (n.max(i + 1), m.max(j + 1)))
+    });
+
+    if rows == 0 || cols == 0 {
+        return Err(format!("load: no entries in '{}'", path));
+    }
+    if let Some(&(i, j, _)) = triplets.iter().find(|&&(i, j, _)| i >= rows || j >= cols) {
+        return Err(format!("load: entry [{}, {}] out of bounds for a [{}, {}] matrice", i, j, rows, cols));
+    }
+    Ok(Sparse::from_triplets(rows, cols, triplets))
+}
+
+#[cfg(test)]
+mod tests {
+    use solver::Solver;
+    use solver::tests::run_in;
+    use std::env;
+    use std::fs;
+
+    fn load(name: &str, contents: &str) -> Solver {
+        let path = env::temp_dir().join(name);
+        let mut solver = Solver::new();
+
+        fs::write(&path, contents).unwrap();
+        run_in(&mut solver, &format!("m = load(\"{}\")", path.display())).unwrap();
+        fs::remove_file(&path).unwrap();
+        solver
+    }
+
+    #[test]
+    fn loading() {
+        let mut solver = load("computorv2_loading.txt", "# shape then entries\n3 3\n0 0 2\n1 1 3\n2 0 1\n");
+
+        assert_eq!(run_in(&mut solver, "full(m) = ?").unwrap(), "[ [2, 0, 0] ; [0, 3, 0] ; [1, 0, 0] ]");
+        assert!(run_in(&mut solver, "load(\"/nonexistent/m.txt\") = ?").unwrap_err().contains("can't open"));
+        assert!(run_in(&mut solver, "load(3) = ?").unwrap_err().contains("expected a quoted path"));
+        run_in(&mut solver, "load = 3").unwrap();
+        assert_eq!(run_in(&mut solver, "load = ?").unwrap(), "3");
+    }
+
+    #[test]
+    fn operations_stay_sparse() {
+        let mut solver = load("computorv2_operations.txt", "0 0 2\n1 1 3\n");
+
+        assert_eq!(run_in(&mut solver, "full(m * 2 + [[1,1];[1,1]]) = ?").unwrap(), "[ [5, 1] ; [1, 7] ]");
+        assert_eq!(run_in(&mut solver, "[[1,1];[1,1]] - m = ?").unwrap(), "[ [-1, 1] ; [1, -2] ]");
+        assert_eq!(run_in(&mut solver, "m - [[1,1];[1,1]] = ?").unwrap(), "[ [1, -1] ; [-1, 2] ]");
+        assert_eq!(run_in(&mut solver, "full(m ^ 3) = ?").unwrap(), "[ [8, 0] ; [0, 27] ]");
+        assert_eq!(run_in(&mut solver, "full(m * [[1,2];[3,4]]) = ?").unwrap(), "[ [2, 0] ; [0, 12] ]");
+        assert_eq!(run_in(&mut solver, "m ** [[1];[1]] = ?").unwrap(), "[ [2] ; [3] ]");
+        assert_eq!(run_in(&mut solver, "trace(m) = ?").unwrap(), "5");
+        assert_eq!(run_in(&mut solver, "det(m) = ?").unwrap(), "6");
+        assert_eq!(run_in(&mut solver, "m[1, 1] = ?").unwrap(), "3");
+        assert_eq!(run_in(&mut solver, "full(m[1:2, :]) = ?").unwrap(), "[ [0, 3] ]");
+    }
+
+    #[test]
+    fn unsupported_operations_are_errors() {
+        let mut solver = load("computorv2_unsupported.txt", "0 0 2\n1 1 3\n");
+
+        for line in &["m % 2", "m + 1", "m ^ (-1)", "m * [[x,1];[1,1]]", "inv(m)", "2 / m"] {
+            assert!(run_in(&mut solver, &format!("{} = ?", line)).unwrap_err().contains("not supported on sparse matrices"), "{}", line);
+        }
+    }
+}
@@ -1,6 +1,11 @@
 use ast::{Expr, Opcode};
 use std::ops::Sub;
-use sub_reduce::{sub_reduce_simple};
+use simplify::simplify;
+use broadcast::{any_matrix, is_scalar, matrix_any, numeric_matrix_matrix};
+use linalg::c_sub;
+use tensor::{element_wise, is_tensor};
+use vector;
+use sparse;
 
 fn sub_number_complex(n: f32, c: (f32, f32)) -> Result<Expr, String> {
     Ok(Expr::Complex(n - c.0, -c.1))
@@ -30,34 +35,9 @@ fn sub_imaginary_complex(c: (f32, f32)) -> Result<Expr, String> {
     Ok(Expr::Complex(c.0, 1.0 - c.1))
 }
 
-fn sub_matrix_matrix(a: Vec<Vec<Box<Expr>>>, b: Vec<Vec<Box<Expr>>>) -> Result<Expr, String> {
-    let mut res = Vec::<Vec<Box<Expr>>>::new();
-    let mut errors = String::new();
-
-    for (y, line) in a.iter().zip(b.iter()).enumerate() {
-        let mut res_line = Vec::<Box<Expr>>::new();
-
-        for (x, value) in line.0.iter().zip(line.1.iter()).enumerate() {
-            let (left, right) = (*value.0.clone(), *value.1.clone());
-            res_line.push(match left - right {
-                Ok(a) => Box::new(a),
-                Err(s) => {
-                    errors = if errors.is_empty() {
-                        format!("{} at [{}, {}]", s, x, y)
-                    } else {
-                        format!("{}\n{} at [{}, {}]", errors, s, x, y)
-                    };
-                    Box::new(Expr::Number(0.0))
-                },
-            });
-        }
-        res.push(res_line);
-    }
-    if errors.is_empty() {
-        Ok(Expr::Matrix(res))
-    } else {
-        Err(errors)
-    }
+// 0 - x rather than -1 * x, for no -0 entries.
+fn negate(matrice: Vec<Vec<Box<Expr>>>) -> Result<Vec<Vec<Box<Expr>>>, String> {
+    matrice.into_iter().map(|line| line.into_iter().map(|x| (Expr::Number(0.0) - *x).map(Box::new)).collect()).collect()
 }
 
 impl Sub for Expr {
@@ -65,6 +45,8 @@ impl Sub for Expr {
 
     fn sub(self, other: Expr) -> Result<Expr, String> {
         match (self, other) {
+            (a, b) if is_tensor(&a) || is_tensor(&b) => element_wise(a, b, |x, y| x - y),
+            (a @ Expr::Vector(_), b) | (a, b @ Expr::Vector(_)) => vector::element_wise(a, b, |x, y| x - y),
             (Expr::Number(a), Expr::Number(b)) => Ok(Expr::Number(a - b)),
             (Expr::Number(a), Expr::Complex(ca, cb)) => sub_number_complex(a, (ca, cb)),
             (Expr::Complex(ca, cb), Expr::Number(a)) => sub_complex_number(a, (ca, cb)),
@@ -73,10 +55,14 @@ impl Sub for Expr {
             (Expr::Imaginary, Expr::Number(a)) => sub_imaginary_number(a),
             (Expr::Complex(ca, cb), Expr::Imaginary) => sub_complex_imaginary((ca, cb)),
             (Expr::Imaginary, Expr::Complex(ca, cb)) => sub_imaginary_complex((ca, cb)),
-            (Expr::Matrix(a), Expr::Matrix(b)) => sub_matrix_matrix(a, b),
-            (Expr::Op(xa, opa, ya), Expr::Op(xb, opb, yb)) => Err(format!("Can't reduce Op Op")),
-            (Expr::Op(a, op, b), c) | (c, Expr::Op(a, op, b)) => sub_reduce_simple(*a, *b, c, op),
-            (a, b) => Ok(Expr::Op(Box::new(a), Opcode::Sub, Box::new(b))),
+            (Expr::Sparse(a), Expr::Sparse(b)) => a.combine(&b, -1.0).map(Expr::Sparse),
+            (Expr::Matrix(a), Expr::Sparse(b)) => b.add_to(a.into_rows()?, -1.0),
+            (Expr::Sparse(a), Expr::Matrix(b)) => a.add_to(negate(b.into_rows()?)?, 1.0),
+            (Expr::Sparse(_), _) | (_, Expr::Sparse(_)) => Err(sparse::unsupported("this subtraction")),
+            (Expr::Matrix(a), Expr::Matrix(b)) => numeric_matrix_matrix(a.into_rows()?, b.into_rows()?, |x, y| Some(c_sub(x, y)), |x, y| x - y),
+            (Expr::Matrix(a), b) if is_scalar(&b) => matrix_any(a.into_rows()?, b, |x, y| x - y),
+            (a, Expr::Matrix(b)) if is_scalar(&a) => any_matrix(a, b.into_rows()?, |x, y| x - y),
+            (a, b) => simplify(Expr::Op(Box::new(a), Opcode::Sub, Box::new(b))),
         }
     }
 }
@@ -0,0 +1,343 @@
+use ast::Expr;
+use solver::Solver;
+use builtins::{check_arity, get_number};
+use sparse;
+
+type Matrice = Vec<Vec<Box<Expr>>>;
+
+// Entries in row-major order, the last axis varying fastest. Matrices are the rank 2 case.
+#[derive(Clone)]
+pub struct Tensor {
+    pub shape: Vec<usize>,
+    pub data: Vec<Expr>,
+}
+
+pub fn fmt_shape(shape: &[usize]) -> String {
+    format!("[{}]", shape.iter().map(|n| n.to_string()).collect::<Vec<_>>().join(", "))
+}
+
+fn dimension_error(expected: &[usize], found: &[usize]) -> String {
+    format!("invalid dimensions\n\texpected: {}\n\tfound: {}", fmt_shape(expected), fmt_shape(found))
+}
+
+// Multi-index of a flat position.
+pub fn position(shape: &[usize], mut flat: usize) -> Vec<usize> {
+    let mut index = vec![0; shape.len()];
+
+    for k in (0..shape.len()).rev() {
+        index[k] = flat % shape[k];
+        flat /= shape[k];
+    }
+    index
+}
+
+fn with_axis(index: &[usize], axis: usize, value: usize) -> Vec<usize> {
+    let mut res = index.to_vec();
+
+    res.insert(axis, value);
+    res
+}
+
+impl Tensor {
+    // Ragged rows are reported as [width, height], like the other matrice errors.
+    pub fn from_matrice(matrice: Matrice) -> Result<Tensor, String> {
+        let (n, m) = (matrice.len(), matrice[0].len());
+
+        if let Some(line) = matrice.iter().find(|line| line.len() != m) {
+            return Err(dimension_error(&[m, n], &[line.len(), n]));
+        }
+        Ok(Tensor { shape: vec![n, m], data: matrice.into_iter().flat_map(|line| line.into_iter().map(|e| *e)).collect() })
+    }
+
+    pub fn from_expr(expr: Expr) -> Result<Tensor, String> {
+        match expr {
+            Expr::Matrix(tensor) => Ok(tensor),
+            Expr::Vector(vector) => Ok(Tensor { shape: vec![vector.len()], data: vector }),
+            Expr::Sparse(_) => Err(sparse::unsupported("tensor operations")),
+            e => Err(format!("expected a tensor, found '{}'", e)),
+        }
+    }
+
+    // Blocks of the same shape along a new first axis, as in a nested literal.
+    pub fn stack(blocks: Vec<Expr>) -> Result<Tensor, String> {
+        let mut shape = vec![blocks.len()];
+        let mut data = Vec::new();
+
+        for (index, block) in blocks.into_iter().enumerate() {
+            let block = Tensor::from_expr(block)?;
+
+            if index == 0 {
+                shape.extend(block.shape.iter().cloned());
+            } else if block.shape[..] != shape[1..] {
+                return Err(dimension_error(&shape[1..], &block.shape));
+            }
+            data.extend(block.data);
+        }
+        Ok(Tensor { shape, data })
+    }
+
+    // Scalars for rank 0 and vectors for rank 1.
+    pub fn into_expr(self) -> Expr {
+        match self.shape.len() {
+            0 => self.data.into_iter().next().unwrap(),
+            1 => Expr::Vector(self.data),
+            _ => Expr::Matrix(self),
+        }
+    }
+
+    pub fn into_rows(self) -> Result<Matrice, String> {
+        if self.shape.len() != 2 {
+            return Err(format!("expected a matrice, found a tensor {}", fmt_shape(&self.shape)));
+        }
+        let mut data = self.data.into_iter();
+        let m = self.shape[1];
+
+        Ok((0..self.shape[0]).map(|_| data.by_ref().take(m).map(Box::new).collect()).collect())
+    }
+
+    pub fn strides(&self) -> Vec<usize> {
+        let mut strides = vec![1; self.shape.len()];
+
+        for k in (1..self.shape.len()).rev() {
+            strides[k - 1] = strides[k] * self.shape[k];
+        }
+        strides
+    }
+
+    fn get(&self, strides: &[usize], index: &[usize]) -> Expr {
+        self.data[index.iter().zip(strides.iter()).map(|(i, s)| i * s).sum::<usize>()].clone()
+    }
+
+    // Applies op on every entry, collecting the errors of each entry.
+    pub fn map<F>(self, mut op: F) -> Result<Tensor, String> where F: FnMut(Expr) -> Result<Expr, String> {
+        let shape = self.shape;
+        let mut errors = Vec::new();
+        let data = self.data.into_iter().enumerate().map(|(flat, e)| match op(e) {
+            Ok(a) => a,
+            Err(s) => {
+                errors.push(format!("{} at {}", s, fmt_shape(&position(&shape, flat))));
+                Expr::Number(0.0)
+            },
+        }).collect();
+
+        if errors.is_empty() {
+            Ok(Tensor { shape, data })
+        } else {
+            Err(errors.join("\n"))
+        }
+    }
+
+    pub fn zip<F>(self, other: Tensor, op: F) -> Result<Tensor, String> where F: Fn(Expr, Expr) -> Result<Expr, String> {
+        if self.shape != other.shape {
+            return Err(dimension_error(&self.shape, &other.shape));
+        }
+        let mut other = other.data.into_iter();
+
+        self.map(|e| op(e, other.next().unwrap()))
+    }
+
+    // Sums over equal indices on axes i < j, removing both.
+    pub fn contract(&self, i: usize, j: usize) -> Result<Tensor, String> {
+        let strides = self.strides();
+        let shape: Vec<usize> = self.shape.iter().enumerate().filter(|&(k, _)| k != i && k != j).map(|(_, &n)| n).collect();
+        let size = shape.iter().product();
+        let mut data = Vec::with_capacity(size);
+
+        for flat in 0..size {
+            let index = position(&shape, flat);
+            let mut sum = Expr::Number(0.0);
+
+            for k in 0..self.shape[i] {
+                sum = (sum + self.get(&strides, &with_axis(&with_axis(&index, i, k), j, k)))?;
+            }
+            data.push(sum);
+        }
+        Ok(Tensor { shape, data })
+    }
+
+    // Sums the products over axis i of self and axis j of other, the remaining axes of self coming first.
+    pub fn tensordot(&self, other: &Tensor, i: usize, j: usize) -> Result<Tensor, String> {
+        let (a_strides, b_strides) = (self.strides(), other.strides());
+        let mut a_shape = self.shape.clone();
+        let mut b_shape = other.shape.clone();
+
+        a_shape.remove(i);
+        b_shape.remove(j);
+        let shape: Vec<usize> = a_shape.iter().chain(b_shape.iter()).cloned().collect();
+        let size = shape.iter().product();
+        let mut data = Vec::with_capacity(size);
+
+        for flat in 0..size {
+            let index = position(&shape, flat);
+            let (a_index, b_index) = index.split_at(a_shape.len());
+            let mut sum = Expr::Number(0.0);
+
+            for k in 0..self.shape[i] {
+                let product = (self.get(&a_strides, &with_axis(a_index, i, k)) * other.get(&b_strides, &with_axis(b_index, j, k)))?;
+
+                sum = (sum + product)?;
+            }
+            data.push(sum);
+        }
+        Ok(Tensor { shape, data })
+    }
+
+    // Axis k of the result is axis axes[k] of self.
+    pub fn permute(&self, axes: &[usize]) -> Tensor {
+        let strides = self.strides();
+        let shape: Vec<usize> = axes.iter().map(|&a| self.shape[a]).collect();
+        let data = (0..self.data.len()).map(|flat| {
+            let index = position(&shape, flat);
+            let mut source = vec![0; axes.len()];
+
+            for (k, &a) in axes.iter().enumerate() {
+                source[a] = index[k];
+            }
+            self.get(&strides, &source)
+        }).collect();
+
+        Tensor { shape, data }
+    }
+}
+
+// Arrays above rank 2, the matrice operations handling the rest.
+pub fn is_tensor(expr: &Expr) -> bool {
+    match *expr {
+        Expr::Matrix(ref t) => t.shape.len() > 2,
+        _ => false,
+    }
+}
+
+// Entry by entry between two arrays of the same shape, or between each entry and any other operand.
+pub fn element_wise<F>(a: Expr, b: Expr, op: F) -> Result<Expr, String> where F: Fn(Expr, Expr) -> Result<Expr, String> {
+    let res = match (a, b) {
+        (a @ Expr::Matrix(_), b @ Expr::Matrix(_)) | (a @ Expr::Matrix(_), b @ Expr::Vector(_)) |
+        (a @ Expr::Vector(_), b @ Expr::Matrix(_)) => Tensor::from_expr(a)?.zip(Tensor::from_expr(b)?, op),
+        (Expr::Matrix(a), b) => a.map(|x| op(x, b.clone())),
+        (a, Expr::Matrix(b)) => b.map(|y| op(a.clone(), y)),
+        (a, b) => return op(a, b),
+    };
+
+    res.map(Tensor::into_expr)
+}
+
+fn get_tensor(solver: &Solver, name: &str, expr: Expr) -> Result<Tensor, String> {
+    Tensor::from_expr(solver.eval(expr)?).map_err(|s| format!("{}: {}", name, s))
+}
+
+fn get_axis(solver: &Solver, name: &str, expr: Expr, rank: usize) -> Result<usize, String> {
+    match get_number(solver, expr)? {
+        a if a >= 0.0 && a == a.round() && (a as usize) < rank => Ok(a as usize),
+        a => Err(format!("{}: expected an axis below {}, found {}", name, rank, a)),
+    }
+}
+
+pub fn shape(solver: &Solver, args: Vec<Expr>) -> Result<Expr, String> {
+    check_arity("shape", &args, 1, 1)?;
+    let tensor = get_tensor(solver, "shape", args[0].clone())?;
+
+    Ok(Expr::Vector(tensor.shape.iter().map(|&n| Expr::Number(n as f32)).collect()))
+}
+
+// contract(T, i, j): trace over two axes of the same size.
+pub fn contract(solver: &Solver, args: Vec<Expr>) -> Result<Expr, String> {
+    check_arity("contract", &args, 3, 3)?;
+    let tensor = get_tensor(solver, "contract", args[0].clone())?;
+    let rank = tensor.shape.len();
+    let (i, j) = (get_axis(solver, "contract", args[1].clone(), rank)?, get_axis(solver, "contract", args[2].clone(), rank)?);
+
+    if i == j {
+        return Err(format!("contract: expected two different axes, found {} twice", i));
+    }
+    if tensor.shape[i] != tensor.shape[j] {
+        return Err(format!("contract: axes {} and {} have different sizes {} and {}", i, j, tensor.shape[i], tensor.shape[j]));
+    }
+    Ok(tensor.contract(i.min(j), i.max(j))?.into_expr())
+}
+
+// tensordot(A, B, i, j): contraction of axis i of A with axis j of B.
+pub fn tensordot(solver: &Solver, args: Vec<Expr>) -> Result<Expr, String> {
+    check_arity("tensordot", &args, 4, 4)?;
+    let a = get_tensor(solver, "tensordot", args[0].clone())?;
+    let b = get_tensor(solver, "tensordot", args[1].clone())?;
+    let i = get_axis(solver, "tensordot", args[2].clone(), a.shape.len())?;
+    let j = get_axis(solver, "tensordot", args[3].clone(), b.shape.len())?;
+
+    if a.shape[i] != b.shape[j] {
+        return Err(format!("tensordot: axes {} and {} have different sizes {} and {}", i, j, a.shape[i], b.shape[j]));
+    }
+    Ok(a.tensordot(&b, i, j)?.into_expr())
+}
+
+// permute(T, a0, a1, ...): axis k of the result is axis ak of T.
+pub fn permute(solver: &Solver, args: Vec<Expr>) -> Result<Expr, String> {
+    check_arity("permute", &args, 2, usize::MAX)?;
+    let tensor = get_tensor(solver, "permute", args[0].clone())?;
+    let rank = tensor.shape.len();
+    let axes: Vec<usize> = args[1..].iter().map(|e| get_axis(solver, "permute", e.clone(), rank)).collect::<Result<_, _>>()?;
+    let mut sorted = axes.clone();
+
+    sorted.sort();
+    if sorted != (0..rank).collect::<Vec<_>>() {
+        return Err(format!("permute: expected a permutation of the {} axes, found {}", rank, fmt_shape(&axes)));
+    }
+    Ok(tensor.permute(&axes).into_expr())
+}
+
+#[cfg(test)]
+mod tests {
+    use solver::Solver;
+    use solver::tests::run_in;
+
+    #[test]
+    fn literals_and_element_wise() {
+        let mut solver = Solver::new();
+
+        run_in(&mut solver, "t = [[[1,2];[3,4]];[[5,6];[7,8]]]").unwrap();
+        assert_eq!(run_in(&mut solver, "shape(t) = ?").unwrap(), "[2, 2, 2]");
+        assert_eq!(run_in(&mut solver, "t + 1 = ?").unwrap(), "[ [ [2, 3] ; [4, 5] ] ; [ [6, 7] ; [8, 9] ] ]");
+        assert_eq!(run_in(&mut solver, "t * t = ?").unwrap(), "[ [ [1, 4] ; [9, 16] ] ; [ [25, 36] ; [49, 64] ] ]");
+        assert_eq!(run_in(&mut solver, "t - t = ?").unwrap(), "[ [ [0, 0] ; [0, 0] ] ; [ [0, 0] ; [0, 0] ] ]");
+        assert!(run_in(&mut solver, "t + [[1,2];[3,4]] = ?").unwrap_err().contains("invalid dimensions"));
+        assert!(run_in(&mut solver, "[[[1,2];[3,4]];[[5,6]]] = ?").is_err());
+        assert!(run_in(&mut solver, "[[1,2];[3]] = ?").unwrap_err().contains("expected: [2, 2]\n\tfound: [1, 2]"));
+    }
+
+    #[test]
+    fn contraction_and_permutation() {
+        let mut solver = Solver::new();
+
+        run_in(&mut solver, "t = [[[1,2];[3,4]];[[5,6];[7,8]]]").unwrap();
+        assert_eq!(run_in(&mut solver, "contract(t, 0, 1) = ?").unwrap(), "[8, 10]");
+        assert_eq!(run_in(&mut solver, "tensordot(t, [1,1], 2, 0) = ?").unwrap(), "[ [3, 7] ; [11, 15] ]");
+        assert_eq!(run_in(&mut solver, "permute(t, 2, 0, 1) = ?").unwrap(), "[ [ [1, 3] ; [5, 7] ] ; [ [2, 4] ; [6, 8] ] ]");
+        assert_eq!(run_in(&mut solver, "transpose(t) = ?").unwrap(), "[ [ [1, 5] ; [3, 7] ] ; [ [2, 6] ; [4, 8] ] ]");
+        assert!(run_in(&mut solver, "contract(t, 0, 0) = ?").is_err());
+        assert!(run_in(&mut solver, "permute(t, 0, 0, 1) = ?").is_err());
+    }
+
+    #[test]
+    fn matrices_are_rank_2_tensors() {
+        let mut solver = Solver::new();
+
+        run_in(&mut solver, "m = [[1,2];[3,4]]").unwrap();
+        assert_eq!(run_in(&mut solver, "shape(m) = ?").unwrap(), "[2, 2]");
+        assert_eq!(run_in(&mut solver, "contract(m, 0, 1) = ?").unwrap(), "5");
+        assert_eq!(run_in(&mut solver, "permute(m, 1, 0) = ?").unwrap(), "[ [1, 3] ; [2, 4] ]");
+        assert_eq!(run_in(&mut solver, "m ** m = ?").unwrap(), "[ [7, 10] ; [15, 22] ]");
+        assert!(run_in(&mut solver, "inv([[[1,2];[3,4]];[[5,6];[7,8]]]) = ?").unwrap_err().contains("found a tensor [2, 2, 2]"));
+    }
+
+    #[test]
+    fn indexing_and_functions() {
+        let mut solver = Solver::new();
+
+        run_in(&mut solver, "t = [[[1,2];[3,4]];[[5,6];[7,8]]]").unwrap();
+        assert_eq!(run_in(&mut solver, "t[1, 0, 1] = ?").unwrap(), "6");
+        assert_eq!(run_in(&mut solver, "t[:, 0, :] = ?").unwrap(), "[ [1, 2] ; [5, 6] ]");
+        run_in(&mut solver, "t[0, 0, 0] = 9").unwrap();
+        assert_eq!(run_in(&mut solver, "t[0, 0, 0] = ?").unwrap(), "9");
+        run_in(&mut solver, "f(x) = [[[x,1];[1,1]];[[1,1];[1,x]]]").unwrap();
+        assert_eq!(run_in(&mut solver, "f(2) = ?").unwrap(), "[ [ [2, 1] ; [1, 1] ] ; [ [1, 1] ; [1, 2] ] ]");
+    }
+}
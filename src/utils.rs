@@ -1 +1,14 @@
-use ast::{Expr};
+use std::f32;
+
+pub fn is_zero(a: f32) -> bool {
+    a >= 0.0 - f32::EPSILON && a <= 0.0 + f32::EPSILON
+}
+
+// A sum is rounding noise when it is negligible next to the largest of its terms.
+pub fn cancel(sum: f32, largest: f32) -> f32 {
+    if sum.abs() <= largest * 4.0 * f32::EPSILON {
+        0.0
+    } else {
+        sum
+    }
+}
@@ -0,0 +1,231 @@
+use ast::{Expr, Pow};
+use solver::Solver;
+use builtins::{check_arity, get_number};
+use tensor::Tensor;
+use linalg::{Complex, c_abs, c_add, c_conj, c_div, c_mul, c_sub, conjugate, from_complex, to_complex};
+
+type Matrice = Vec<Vec<Box<Expr>>>;
+
+pub fn to_column(vector: Vec<Expr>) -> Matrice {
+    vector.into_iter().map(|e| vec![Box::new(e)]).collect()
+}
+
+pub fn to_row(vector: Vec<Expr>) -> Matrice {
+    vec![vector.into_iter().map(Box::new).collect()]
+}
+
+// Tensor::map on the vector as a rank-1 tensor.
+pub fn map_entries<F>(vector: Vec<Expr>, op: F) -> Result<Expr, String> where F: FnMut(Expr) -> Result<Expr, String> {
+    Ok(Tensor { shape: vec![vector.len()], data: vector }.map(op)?.into_expr())
+}
+
+// Entry by entry between vectors of the same size, or between each entry and any other operand.
+// Against a matrice the vector is taken as a column.
+pub fn element_wise<F>(a: Expr, b: Expr, op: F) -> Result<Expr, String> where F: Fn(Expr, Expr) -> Result<Expr, String> {
+    match (a, b) {
+        (Expr::Vector(x), Expr::Vector(y)) => {
+            if x.len() != y.len() {
+                return Err(format!("invalid dimensions\n\texpected: [{}]\n\tfound: [{}]", x.len(), y.len()));
+            }
+            let mut y = y.into_iter();
+
+            map_entries(x, |x| op(x, y.next().unwrap()))
+        },
+        (Expr::Vector(x), b @ Expr::Matrix(_)) | (Expr::Vector(x), b @ Expr::Sparse(_)) => op(Expr::matrix(to_column(x)), b),
+        (a @ Expr::Matrix(_), Expr::Vector(y)) | (a @ Expr::Sparse(_), Expr::Vector(y)) => op(a, Expr::matrix(to_column(y))),
+        (Expr::Vector(x), b) => map_entries(x, |x| op(x, b.clone())),
+        (a, Expr::Vector(y)) => map_entries(y, |y| op(a.clone(), y)),
+        (a, b) => op(a, b),
+    }
+}
+
+// A vector, or a matrice with a single row or column.
+pub fn get_vector(solver: &Solver, name: &str, expr: Expr) -> Result<Vec<Expr>, String> {
+    match solver.eval(expr)? {
+        Expr::Vector(vector) => Ok(vector),
+        Expr::Matrix(t) if t.shape.len() == 2 && (t.shape[0] == 1 || t.shape[1] == 1) => Ok(t.data),
+        e => Err(format!("{}: expected a vector, found '{}'", name, e)),
+    }
+}
+
+fn get_numeric(solver: &Solver, name: &str, expr: Expr) -> Result<Vec<Complex>, String> {
+    match get_vector(solver, name, expr)?.iter().map(to_complex).collect() {
+        Some(vector) => Ok(vector),
+        None => Err(format!("{}: expected numeric entries", name)),
+    }
+}
+
+fn from_numeric(vector: Vec<Complex>) -> Expr {
+    Expr::Vector(vector.into_iter().map(from_complex).collect())
+}
+
+fn length(vector: &[Complex]) -> f64 {
+    vector.iter().map(|&a| c_abs(a).powi(2)).sum::<f64>().sqrt()
+}
+
+// Sum of u_i * v_i, the u ** v of two vectors like the row by column product of matrices.
+pub fn product(name: &str, u: Vec<Expr>, v: Vec<Expr>) -> Result<Expr, String> {
+    if u.len() != v.len() {
+        return Err(format!("{}: vectors of different sizes {} and {}", name, u.len(), v.len()));
+    }
+    let res = u.into_iter().zip(v).try_fold(Expr::Number(0.0), |acc, (a, b)| acc + (a * b)?)?;
+
+    Ok(to_complex(&res).map(from_complex).unwrap_or(res))
+}
+
+// Sum of conj(u_i) * v_i, variables being taken as real.
+fn inner(name: &str, u: Vec<Expr>, v: Vec<Expr>) -> Result<Expr, String> {
+    product(name, u.into_iter().map(conjugate).collect::<Result<_, _>>()?, v)
+}
+
+pub fn dot(solver: &Solver, args: Vec<Expr>) -> Result<Expr, String> {
+    check_arity("dot", &args, 2, 2)?;
+    inner("dot", get_vector(solver, "dot", args[0].clone())?, get_vector(solver, "dot", args[1].clone())?)
+}
+
+pub fn cross(solver: &Solver, args: Vec<Expr>) -> Result<Expr, String> {
+    check_arity("cross", &args, 2, 2)?;
+    let u = get_vector(solver, "cross", args[0].clone())?;
+    let v = get_vector(solver, "cross", args[1].clone())?;
+
+    if u.len() != 3 || v.len() != 3 {
+        return Err(format!("cross: expected vectors of size 3, found {} and {}", u.len(), v.len()));
+    }
+    let term = |i: usize, j: usize| (u[i].clone() * v[j].clone())? - (u[j].clone() * v[i].clone())?;
+
+    Ok(Expr::Vector(vec![term(1, 2)?, term(2, 0)?, term(0, 1)?]))
+}
+
+// norm(v[, p]), p >= 1 or inf, 2 by default.
+pub fn norm(solver: &Solver, args: Vec<Expr>) -> Result<Expr, String> {
+    check_arity("norm", &args, 1, 2)?;
+    let p = match args.get(1) {
+        Some(Expr::Variable(s)) if s.to_lowercase() == "inf" => None,
+        Some(e) => Some(get_number(solver, e.clone())? as f64),
+        None => Some(2.0),
+    };
+    let vector = get_vector(solver, "norm", args[0].clone())?;
+    let numeric: Option<Vec<f64>> = vector.iter().map(|e| to_complex(e).map(c_abs)).collect();
+
+    match (p, numeric) {
+        (Some(p), _) if p < 1.0 => Err(format!("norm: expected p >= 1 or inf, found {}", p)),
+        (None, Some(moduli)) => Ok(Expr::Number(moduli.into_iter().fold(0.0, f64::max) as f32)),
+        (Some(p), Some(moduli)) => Ok(Expr::Number(moduli.into_iter().map(|a| a.powf(p)).sum::<f64>().powf(1.0 / p) as f32)),
+        (Some(2.0), None) => inner("norm", vector.clone(), vector)?.pow(Expr::Number(0.5)),
+        _ => Err(format!("norm: expected numeric entries")),
+    }
+}
+
+pub fn normalize(solver: &Solver, args: Vec<Expr>) -> Result<Expr, String> {
+    check_arity("normalize", &args, 1, 1)?;
+    let vector = get_numeric(solver, "normalize", args[0].clone())?;
+    let length = length(&vector);
+
+    if length == 0.0 {
+        return Err(format!("normalize: can't normalize the zero vector"));
+    }
+    Ok(from_numeric(vector.into_iter().map(|a| (a.0 / length, a.1 / length)).collect()))
+}
+
+// proj(u, v): orthogonal projection of u on the line of v.
+pub fn proj(solver: &Solver, args: Vec<Expr>) -> Result<Expr, String> {
+    check_arity("proj", &args, 2, 2)?;
+    let u = get_vector(solver, "proj", args[0].clone())?;
+    let v = get_vector(solver, "proj", args[1].clone())?;
+    let factor = (inner("proj", v.clone(), u)? / inner("proj", v.clone(), v.clone())?)?;
+
+    map_entries(v, |a| factor.clone() * a)
+}
+
+// Orthonormal basis of the span of the arguments, in order, dropping the dependent vectors.
+// Always a list, shorter than the arguments when some were dropped.
+pub fn gramschmidt(solver: &Solver, args: Vec<Expr>) -> Result<Expr, String> {
+    check_arity("gramschmidt", &args, 1, usize::MAX)?;
+    let vectors = args.into_iter().map(|e| get_numeric(solver, "gramschmidt", e)).collect::<Result<Vec<_>, _>>()?;
+    let size = vectors[0].len();
+
+    if let Some(v) = vectors.iter().find(|v| v.len() != size) {
+        return Err(format!("gramschmidt: vectors of different sizes {} and {}", size, v.len()));
+    }
+    // Entries are f32, so is the rounding left in a dependent vector.
+    let epsilon = vectors.iter().map(|v| length(v)).fold(0.0, f64::max) * size as f64 * 8.0 * f32::EPSILON as f64;
+    let mut basis: Vec<Vec<Complex>> = Vec::new();
+
+    for mut v in vectors.iter().cloned() {
+        // Modified Gram-Schmidt, removing each component from the updated vector
+        for q in basis.iter() {
+            let coef = q.iter().zip(v.iter()).fold((0.0, 0.0), |acc, (&a, &b)| c_add(acc, c_mul(c_conj(a), b)));
+
+            for (a, &b) in v.iter_mut().zip(q.iter()) {
+                *a = c_sub(*a, c_mul(coef, b));
+            }
+        }
+        let length = length(&v);
+
+        if length > epsilon {
+            basis.push(v.into_iter().map(|a| c_div(a, (length, 0.0))).collect());
+        }
+    }
+    if basis.is_empty() {
+        return Err(format!("gramschmidt: all the vectors are zero"));
+    }
+    Ok(Expr::List(basis.into_iter().map(|v| Box::new(from_numeric(v))).collect()))
+}
+
+#[cfg(test)]
+mod tests {
+    use solver::tests::run;
+
+    #[test]
+    fn element_wise_operations() {
+        assert_eq!(run("[1,2,3] + [4,5,6]").unwrap(), "[5, 7, 9]");
+        assert_eq!(run("[1,2,3] * 2").unwrap(), "[2, 4, 6]");
+        assert_eq!(run("1 - [1,2]").unwrap(), "[0, -1]");
+        assert!(run("[1,2] + [1,2,3]").unwrap_err().contains("invalid dimensions"));
+    }
+
+    #[test]
+    fn products() {
+        assert_eq!(run("dot([1,2,3],[4,5,6])").unwrap(), "32");
+        assert_eq!(run("[1,2,3] ** [4,5,6]").unwrap(), "32");
+        assert_eq!(run("cross([1,0,0],[0,1,0])").unwrap(), "[0, 0, 1]");
+        assert!(run("cross([1,0],[0,1])").is_err());
+        assert!(run("[1,2] ** [1,2,3]").is_err());
+    }
+
+    #[test]
+    fn only_dot_conjugates() {
+        assert_eq!(run("dot([1,i],[1,i])").unwrap(), "2");
+        assert_eq!(run("dot([i,0],[1,0])").unwrap(), "0 -1i");
+        assert_eq!(run("[1,i] ** [1,i]").unwrap(), "0");
+        assert_eq!(run("[[1,i]] ** [[1];[i]]").unwrap(), "[ [0] ]");
+    }
+
+    #[test]
+    fn norms() {
+        assert_eq!(run("norm([3,4])").unwrap(), "5");
+        assert_eq!(run("norm([3,(-4)], 1)").unwrap(), "7");
+        assert_eq!(run("norm([3,(-4)], inf)").unwrap(), "4");
+        assert_eq!(run("normalize([3,4])").unwrap(), "[0.6, 0.8]");
+        assert!(run("normalize([0,0])").is_err());
+        assert_eq!(run("proj([1,1],[2,0])").unwrap(), "[1, 0]");
+    }
+
+    #[test]
+    fn gram_schmidt() {
+        assert_eq!(run("gramschmidt([1,0],[1,1])").unwrap(), "[1, 0], [0, 1]");
+        assert_eq!(run("gramschmidt([1,0],[2,0],[0,3])").unwrap(), "[1, 0], [0, 1]");
+        assert_eq!(run("gramschmidt([1,0],[2,0])[0]").unwrap(), "[1, 0]");
+        assert!(run("gramschmidt([0,0])").is_err());
+        assert!(run("gramschmidt([1,0],[1,0,0])").is_err());
+    }
+
+    #[test]
+    fn rounding_is_not_a_new_direction() {
+        for line in &["gramschmidt([0.1,0.2,0.3],[0.3,0.6,0.9])", "gramschmidt([0.7,0.3,0.11],[2.1,0.9,0.33])"] {
+            let res = run(line).unwrap();
+
+            assert_eq!(res.matches('[').count(), 1, "{} gave {}", line, res);
+        }
+    }
+}